@@ -1,4 +1,5 @@
 pub use allocator_api2::alloc::{handle_alloc_error, Allocator};
+pub use allocator_api2::boxed::Box;
 pub use allocator_api2::vec::Vec;
 
 #[cfg(not(debug_assertions))]
@@ -9,17 +10,29 @@ pub use inner::Global;
 
 #[cfg(debug_assertions)]
 mod inner {
+    extern crate alloc;
+    use alloc::rc::Rc;
     use allocator_api2::alloc::{AllocError, Allocator};
     use core::alloc::Layout;
+    use core::cell::Cell;
     use core::ptr::NonNull;
+    /// Counts live allocations and panics if any are left when the last clone drops.
+    ///
+    /// Clones share one count rather than keeping their own. The [`Allocator`]
+    /// contract lets memory be freed through any clone of the allocator that
+    /// allocated it, and the maps rely on that: `split_by` and `remove_range` move
+    /// nodes into a map holding a clone, which later frees them. A count per clone
+    /// would report a leak in the first map and an underflow in the second.
+    ///
+    /// Freeing more than was allocated still panics at the faulty call.
     pub struct Global {
-        count: usize,
+        count: Rc<Cell<usize>>,
         alloc: allocator_api2::alloc::Global,
     }
     impl Default for Global {
         fn default() -> Self {
             Self {
-                count: 0,
+                count: Rc::new(Cell::new(0)),
                 alloc: allocator_api2::alloc::Global,
             }
         }
@@ -27,14 +40,14 @@ mod inner {
     impl Clone for Global {
         fn clone(&self) -> Self {
             Self {
-                count: 0,
-                alloc: self.alloc.clone(),
+                count: self.count.clone(),
+                alloc: self.alloc,
             }
         }
     }
     impl Drop for Global {
         fn drop(&mut self) {
-            if self.count != 0 {
+            if Rc::strong_count(&self.count) == 1 && self.count.get() != 0 {
                 panic!("Memory leak detected");
             }
         }
@@ -42,25 +55,21 @@ mod inner {
     unsafe impl Allocator for Global {
         #[inline(always)]
         fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-            unsafe {
-                (&self.count as *const usize as *mut usize).write(self.count + 1);
-            }
+            self.count.set(self.count.get() + 1);
             self.alloc.allocate(layout)
         }
 
         #[inline(always)]
         fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-            unsafe {
-                (&self.count as *const usize as *mut usize).write(self.count + 1);
-            }
+            self.count.set(self.count.get() + 1);
             self.alloc.allocate_zeroed(layout)
         }
 
         #[inline(always)]
         unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
-            unsafe {
-                (&self.count as *const usize as *mut usize).write(self.count - 1);
-            }
+            let count = self.count.get().checked_sub(1);
+            let count = count.expect("freed memory that was never allocated");
+            self.count.set(count);
             self.alloc.deallocate(ptr, layout);
         }
 
@@ -1,9 +1,41 @@
 pub mod rbtree;
-pub use rbtree::RBTreeMap;
+pub use rbtree::{RBTreeMap, RBTreeMultiSet};
 
 pub mod rbtree_map {
-    pub use super::rbtree::{Entry, OccupiedEntry, VacantEntry};
+    pub use super::rbtree::{
+        Change, Comparable, Diff, Entry, Handle, IntoIter, InvariantViolation, MergeJoin,
+        MergeJoinItem, Observer, OccupiedEntry, Parts, Prefix, Range, Transaction, TreeStats,
+        VacantEntry,
+    };
 }
 
 pub mod fuzzy;
 pub use fuzzy::Finder as FuzzyFinder;
+
+pub mod priority_queue;
+pub use priority_queue::IndexedPriorityQueue;
+
+pub mod expiring;
+#[cfg(feature = "std")]
+pub use expiring::SystemClock;
+pub use expiring::{Clock, ExpiringMap};
+
+pub mod frozen;
+pub use frozen::FrozenMap;
+
+pub mod static_map;
+pub use static_map::StaticRBTreeMap;
+
+pub mod small_map;
+pub use small_map::SmallRBTreeMap;
+
+pub mod multi_index;
+pub use multi_index::MultiIndexMap;
+
+pub mod bimap;
+pub use bimap::RBTreeBiMap;
+
+#[cfg(feature = "std")]
+pub mod durable;
+#[cfg(feature = "std")]
+pub use durable::DurableRBTreeMap;
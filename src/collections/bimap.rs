@@ -0,0 +1,290 @@
+use super::{rbtree_map::Range, RBTreeMap};
+use core::{borrow::Borrow, fmt::Debug, ops::RangeBounds};
+
+/// The pairs an [`RBTreeBiMap::insert`] displaced to keep the map one-to-one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Overwritten<L, R> {
+    /// Neither value was present.
+    Neither,
+    /// The left value was present, paired with another right value.
+    Left(L, R),
+    /// The right value was present, paired with another left value.
+    Right(L, R),
+    /// The same pair was already present.
+    Pair(L, R),
+    /// Both values were present, in two different pairs: the one with the left
+    /// value first.
+    Both((L, R), (L, R)),
+}
+
+impl<L, R> Overwritten<L, R> {
+    /// Returns `true` if a pair was displaced.
+    pub fn did_overwrite(&self) -> bool {
+        !matches!(self, Overwritten::Neither)
+    }
+}
+
+/// A one-to-one map between left and right values, ordered on both sides.
+///
+/// Two red-black trees hold every pair, one keyed by left and one by right
+/// value, so each value is cloned into the other tree. Lookups, removals,
+/// iteration and ranges are available from either side in `O(log n)`.
+///
+/// # Overwriting
+///
+/// Each value is in at most one pair. [`insert`](Self::insert) removes the
+/// pairs holding either of its values before adding the new one, and returns
+/// them as [`Overwritten`]; [`insert_no_overwrite`](Self::insert_no_overwrite)
+/// refuses instead.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::bimap::Overwritten;
+/// use xsl::collections::RBTreeBiMap;
+///
+/// let mut symbols = RBTreeBiMap::new();
+/// symbols.insert("add", 1);
+/// symbols.insert("sub", 2);
+/// symbols.insert("mul", 3);
+///
+/// assert_eq!(symbols.get_by_left("sub"), Some(&2));
+/// assert_eq!(symbols.get_by_right(&3), Some(&"mul"));
+/// assert!(symbols.iter_left().map(|(l, _)| *l).eq(["add", "mul", "sub"]));
+/// assert!(symbols.range_by_right(2..).map(|(_, l)| *l).eq(["sub", "mul"]));
+///
+/// // "add" takes id 3, so "mul" loses it and "add" drops 1
+/// assert_eq!(symbols.insert("add", 3), Overwritten::Both(("add", 1), ("mul", 3)));
+/// assert_eq!(symbols.len(), 2);
+/// assert_eq!(symbols.remove_by_right(&2), Some(("sub", 2)));
+/// ```
+#[derive(Clone)]
+pub struct RBTreeBiMap<L, R> {
+    left: RBTreeMap<L, R>,
+    right: RBTreeMap<R, L>,
+}
+
+impl<L, R> Default for RBTreeBiMap<L, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L, R> Debug for RBTreeBiMap<L, R>
+where
+    L: Debug,
+    R: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.left.iter()).finish()
+    }
+}
+
+impl<L, R> PartialEq for RBTreeBiMap<L, R>
+where
+    L: PartialEq,
+    R: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.left == other.left
+    }
+}
+
+impl<L, R> Eq for RBTreeBiMap<L, R>
+where
+    L: Eq,
+    R: Eq,
+{
+}
+
+impl<L, R> RBTreeBiMap<L, R> {
+    /// Makes a new, empty `RBTreeBiMap`.
+    pub fn new() -> Self {
+        Self {
+            left: RBTreeMap::new(),
+            right: RBTreeMap::new(),
+        }
+    }
+    /// Returns the number of pairs.
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+    /// Returns `true` if there are no pairs.
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+    /// Removes every pair.
+    pub fn clear(&mut self) {
+        self.left.clear();
+        self.right.clear();
+    }
+}
+
+impl<L, R> RBTreeBiMap<L, R>
+where
+    L: Ord,
+    R: Ord,
+{
+    /// Gets an iterator over the pairs as `(left, right)`, sorted by left value.
+    pub fn iter_left(&self) -> Range<'_, L, R> {
+        self.left.range::<L, _>(..)
+    }
+    /// Gets an iterator over the pairs as `(right, left)`, sorted by right value.
+    pub fn iter_right(&self) -> Range<'_, R, L> {
+        self.right.range::<R, _>(..)
+    }
+    /// Gets an iterator over the pairs as `(left, right)` whose left values
+    /// are in `range`, sorted by left value.
+    pub fn range_by_left<Q, T>(&self, range: T) -> Range<'_, L, R>
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Ord,
+        T: RangeBounds<Q>,
+    {
+        self.left.range(range)
+    }
+    /// Gets an iterator over the pairs as `(right, left)` whose right values
+    /// are in `range`, sorted by right value.
+    pub fn range_by_right<Q, T>(&self, range: T) -> Range<'_, R, L>
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Ord,
+        T: RangeBounds<Q>,
+    {
+        self.right.range(range)
+    }
+    /// Returns the right value paired with `left`.
+    pub fn get_by_left<Q>(&self, left: &Q) -> Option<&R>
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.left.get(left)
+    }
+    /// Returns the left value paired with `right`.
+    pub fn get_by_right<Q>(&self, right: &Q) -> Option<&L>
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.right.get(right)
+    }
+    /// Returns `true` if `left` is in a pair.
+    pub fn contains_left<Q>(&self, left: &Q) -> bool
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.left.contains_key(left)
+    }
+    /// Returns `true` if `right` is in a pair.
+    pub fn contains_right<Q>(&self, right: &Q) -> bool
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.right.contains_key(right)
+    }
+    /// Removes the pair holding `left` and returns it.
+    pub fn remove_by_left<Q>(&mut self, left: &Q) -> Option<(L, R)>
+    where
+        L: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (left, right) = self.left.remove_entry(left)?;
+        self.right.remove(&right);
+        Some((left, right))
+    }
+    /// Removes the pair holding `right` and returns it.
+    pub fn remove_by_right<Q>(&mut self, right: &Q) -> Option<(L, R)>
+    where
+        R: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (right, left) = self.right.remove_entry(right)?;
+        self.left.remove(&left);
+        Some((left, right))
+    }
+}
+
+impl<L, R> RBTreeBiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    /// Pairs `left` with `right`, removing the pairs that held either before.
+    ///
+    /// See [`Overwritten`] for the pairs returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::bimap::Overwritten;
+    /// use xsl::collections::RBTreeBiMap;
+    ///
+    /// let mut map = RBTreeBiMap::new();
+    /// assert_eq!(map.insert('a', 1), Overwritten::Neither);
+    /// assert_eq!(map.insert('a', 1), Overwritten::Pair('a', 1));
+    /// assert_eq!(map.insert('a', 2), Overwritten::Left('a', 1));
+    /// assert_eq!(map.insert('b', 2), Overwritten::Right('a', 2));
+    /// assert_eq!(map.get_by_left(&'a'), None);
+    /// ```
+    pub fn insert(&mut self, left: L, right: R) -> Overwritten<L, R> {
+        let overwritten = match (self.remove_by_left(&left), self.remove_by_right(&right)) {
+            (None, None) => Overwritten::Neither,
+            (Some((l, r)), None) if r == right => Overwritten::Pair(l, r),
+            (Some((l, r)), None) => Overwritten::Left(l, r),
+            (None, Some((l, r))) => Overwritten::Right(l, r),
+            (Some(by_left), Some(by_right)) => Overwritten::Both(by_left, by_right),
+        };
+        self.left.insert(left.clone(), right.clone());
+        self.right.insert(right, left);
+        overwritten
+    }
+    /// Pairs `left` with `right` if neither is in a pair yet, or hands them
+    /// back as `Err((left, right))`.
+    pub fn insert_no_overwrite(&mut self, left: L, right: R) -> Result<(), (L, R)> {
+        if self.contains_left(&left) || self.contains_right(&right) {
+            return Err((left, right));
+        }
+        self.left.insert(left.clone(), right.clone());
+        self.right.insert(right, left);
+        Ok(())
+    }
+}
+
+impl<L, R, const N: usize> From<[(L, R); N]> for RBTreeBiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    fn from(arr: [(L, R); N]) -> Self {
+        let mut map = Self::new();
+        map.extend(arr);
+        map
+    }
+}
+
+impl<L, R> FromIterator<(L, R)> for RBTreeBiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    fn from_iter<T: IntoIterator<Item = (L, R)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<L, R> Extend<(L, R)> for RBTreeBiMap<L, R>
+where
+    L: Ord + Clone,
+    R: Ord + Clone,
+{
+    fn extend<T: IntoIterator<Item = (L, R)>>(&mut self, iter: T) {
+        for (left, right) in iter {
+            self.insert(left, right);
+        }
+    }
+}
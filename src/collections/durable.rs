@@ -0,0 +1,476 @@
+//! An [`RBTreeMap`] kept on disk as an append-only operation log.
+//!
+//! The log starts with a magic header, followed by records of the form
+//! `[payload length: u32][CRC-32 of payload: u32][payload]`, all little-endian.
+//! The first byte of a payload names the operation. A snapshot written by
+//! [`DurableRBTreeMap::compact`] is a run of entry records in key order at the
+//! head of the log.
+extern crate std;
+
+use super::RBTreeMap;
+use core::ops::Deref;
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    string::String,
+    vec::Vec,
+};
+
+const MAGIC: &[u8; 8] = b"xsl-log\x01";
+
+const ENTRY: u8 = 0;
+const INSERT: u8 = 1;
+const REMOVE: u8 = 2;
+const CLEAR: u8 = 3;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => 0xEDB8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// The IEEE CRC-32 of `bytes`.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &byte| {
+        CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Types that can be written to a [`DurableRBTreeMap`] log and read back.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::durable::Codec;
+///
+/// let mut bytes = Vec::new();
+/// (String::from("key"), 7u32).encode(&mut bytes);
+/// assert_eq!(
+///     <(String, u32)>::decode(&mut bytes.as_slice()),
+///     Some((String::from("key"), 7))
+/// );
+/// ```
+pub trait Codec: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+    /// Decodes a value from the front of `input` and advances past it, or
+    /// returns `None` if the bytes do not hold one.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Some(head)
+}
+
+macro_rules! impl_codec_int {
+    ($($ty:ty),*) => {$(
+        impl Codec for $ty {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let bytes = take(input, core::mem::size_of::<$ty>())?;
+                Some(<$ty>::from_le_bytes(bytes.try_into().unwrap()))
+            }
+        }
+    )*};
+}
+
+impl_codec_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Codec for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        u64::decode(input)?.try_into().ok()
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for () {
+    fn encode(&self, _: &mut Vec<u8>) {}
+    fn decode(_: &mut &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Codec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+impl<T> Codec for Vec<T>
+where
+    T: Codec,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for item in self {
+            item.encode(out);
+        }
+    }
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let len = usize::decode(input)?;
+        // every item takes at least a byte, except zero-sized ones
+        let mut items = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            items.push(T::decode(input)?);
+        }
+        Some(items)
+    }
+}
+
+macro_rules! impl_codec_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: Codec),*> Codec for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn encode(&self, out: &mut Vec<u8>) {
+                let ($($name,)*) = self;
+                $($name.encode(out);)*
+            }
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                Some(($($name::decode(input)?,)*))
+            }
+        }
+    };
+}
+
+impl_codec_tuple!(A, B);
+impl_codec_tuple!(A, B, C);
+impl_codec_tuple!(A, B, C, D);
+
+/// When a [`DurableRBTreeMap`] forces appended records to disk.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Sync after every mutation, so a mutation that returned survives a crash.
+    #[default]
+    Always,
+    /// Sync after every `n` mutations; a crash loses at most the last `n - 1`.
+    EveryN(usize),
+    /// Only sync on [`sync`](DurableRBTreeMap::sync), [`compact`](DurableRBTreeMap::compact)
+    /// and drop, leaving the rest to the operating system.
+    Never,
+}
+
+/// An ordered map that survives restarts, replayed from an append-only log.
+///
+/// Reads go straight to the in-memory [`RBTreeMap`] the map dereferences to.
+/// Each mutation appends a checksummed record before it is applied, and the
+/// [`SyncPolicy`] decides when records are synced. Reopening replays the log; a
+/// record torn by a crash fails its checksum, and the log is cut back to the
+/// last whole record. [`compact`](Self::compact) rewrites the log as a sorted
+/// snapshot, which loads in linear time.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::DurableRBTreeMap;
+///
+/// let path = std::env::temp_dir().join(format!("xsl-doc-{}.log", std::process::id()));
+/// # let _ = std::fs::remove_file(&path);
+/// {
+///     let mut map = DurableRBTreeMap::open(&path)?;
+///     map.insert(String::from("b"), 2u32)?;
+///     map.insert(String::from("a"), 1)?;
+///     map.remove(&String::from("b"))?;
+/// }
+/// let map = DurableRBTreeMap::<String, u32>::open(&path)?;
+/// assert!(map.iter().eq([(&String::from("a"), &1)]));
+/// # std::fs::remove_file(&path)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct DurableRBTreeMap<K, V> {
+    map: RBTreeMap<K, V>,
+    file: File,
+    path: PathBuf,
+    policy: SyncPolicy,
+    /// Records appended since the last sync.
+    unsynced: usize,
+    /// Records appended since the snapshot at the head of the log.
+    log_len: usize,
+    /// The bytes of whole records in the log, where the next record starts.
+    file_len: u64,
+    /// Set when a failed append could not be cut off the log, which then
+    /// refuses further appends.
+    poisoned: bool,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Splits the next whole record off `input`, returning its payload, or `None`
+/// at the end of the log or at a torn or corrupt record.
+fn next_record<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let mut rest = *input;
+    let len = u32::decode(&mut rest)? as usize;
+    let crc = u32::decode(&mut rest)?;
+    let payload = take(&mut rest, len)?;
+    if payload.is_empty() || crc32(payload) != crc {
+        return None;
+    }
+    *input = rest;
+    Some(payload)
+}
+
+impl<K, V> DurableRBTreeMap<K, V>
+where
+    K: Ord + Codec,
+    V: Codec,
+{
+    /// Opens the map logged at `path`, creating an empty log if there is none,
+    /// and syncs after every mutation.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the file cannot be read or written, is not a log, or holds
+    /// records that do not decode as `K` and `V`.
+    pub fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::open_with(path, SyncPolicy::default())
+    }
+    /// Opens the map logged at `path` like [`open`](Self::open), syncing by `policy`.
+    pub fn open_with<P>(path: P, policy: SyncPolicy) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if bytes.is_empty() {
+            file.write_all(MAGIC)?;
+            file.sync_all()?;
+            bytes.extend_from_slice(MAGIC);
+        }
+        if !bytes.starts_with(MAGIC) {
+            return Err(invalid_data("not an RBTreeMap log"));
+        }
+        let mut input = &bytes[MAGIC.len()..];
+        let malformed = || invalid_data("log record does not decode");
+        // the snapshot at the head of the log, already in key order
+        let mut snapshot = Vec::new();
+        loop {
+            let mut rest = input;
+            let Some([ENTRY, payload @ ..]) = next_record(&mut rest) else {
+                break;
+            };
+            snapshot.push(<(K, V)>::decode(&mut &payload[..]).ok_or_else(malformed)?);
+            input = rest;
+        }
+        let mut map = RBTreeMap::bulk_build_from_sorted_iter(snapshot, Default::default());
+        let mut log_len = 0;
+        while let Some(payload) = next_record(&mut input) {
+            let (&kind, mut payload) = payload.split_first().unwrap();
+            match kind {
+                INSERT => {
+                    let (k, v) = <(K, V)>::decode(&mut payload).ok_or_else(malformed)?;
+                    map.insert(k, v);
+                }
+                REMOVE => {
+                    map.remove(&K::decode(&mut payload).ok_or_else(malformed)?);
+                }
+                CLEAR => map.clear(),
+                _ => return Err(malformed()),
+            }
+            log_len += 1;
+        }
+        let file_len = (bytes.len() - input.len()) as u64;
+        if !input.is_empty() {
+            // a torn tail left by a crash mid-append
+            file.set_len(file_len)?;
+            file.sync_all()?;
+        }
+        Ok(Self {
+            map,
+            file,
+            path,
+            policy,
+            unsynced: 0,
+            log_len,
+            file_len,
+            poisoned: false,
+        })
+    }
+    /// Appends one record, syncing as the policy says.
+    ///
+    /// If the record cannot be written or synced it is cut off the log again,
+    /// so a torn record never sits before later ones; if even that fails, the
+    /// map is poisoned and every later append fails.
+    fn append(&mut self, kind: u8, encode: impl FnOnce(&mut Vec<u8>)) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other("log left torn by a failed append"));
+        }
+        let mut payload = Vec::from([kind]);
+        encode(&mut payload);
+        let mut record = Vec::with_capacity(payload.len() + 8);
+        (payload.len() as u32).encode(&mut record);
+        crc32(&payload).encode(&mut record);
+        record.extend_from_slice(&payload);
+        let sync = match self.policy {
+            SyncPolicy::Always => true,
+            SyncPolicy::EveryN(n) => self.unsynced + 1 >= n,
+            SyncPolicy::Never => false,
+        };
+        let written = self.file.write_all(&record).and_then(|()| {
+            if sync {
+                self.file.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(err) = written {
+            if self.file.set_len(self.file_len).is_err() {
+                self.poisoned = true;
+            }
+            return Err(err);
+        }
+        self.file_len += record.len() as u64;
+        self.log_len += 1;
+        self.unsynced = if sync { 0 } else { self.unsynced + 1 };
+        Ok(())
+    }
+    /// Inserts a key-value pair, logging it first, and returns the old value.
+    ///
+    /// If the record cannot be appended the map is left unchanged.
+    pub fn insert(&mut self, key: K, value: V) -> io::Result<Option<V>> {
+        self.append(INSERT, |out| {
+            key.encode(out);
+            value.encode(out);
+        })?;
+        Ok(self.map.insert(key, value))
+    }
+    /// Removes a key, logging it first if it is present, and returns its value.
+    pub fn remove(&mut self, key: &K) -> io::Result<Option<V>> {
+        if !self.map.contains_key(key) {
+            return Ok(None);
+        }
+        self.append(REMOVE, |out| key.encode(out))?;
+        Ok(self.map.remove(key))
+    }
+    /// Removes every entry, logging it first.
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.append(CLEAR, |_| {})?;
+        self.map.clear();
+        Ok(())
+    }
+    /// Forces every appended record to disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+    /// Returns the number of operation records logged after the snapshot,
+    /// which [`compact`](Self::compact) folds away.
+    pub fn log_len(&self) -> usize {
+        self.log_len
+    }
+    /// Rewrites the log as a sorted snapshot of the current entries.
+    ///
+    /// The snapshot is written beside the log, synced, and renamed over it, so a
+    /// crash leaves either the old log or the new one.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let mut tmp = OsString::from(self.path.as_os_str());
+        tmp.push(".compact");
+        let tmp = PathBuf::from(tmp);
+        let mut bytes = Vec::from(*MAGIC);
+        let mut payload = Vec::new();
+        for (k, v) in self.map.iter() {
+            payload.clear();
+            payload.push(ENTRY);
+            k.encode(&mut payload);
+            v.encode(&mut payload);
+            (payload.len() as u32).encode(&mut bytes);
+            crc32(&payload).encode(&mut bytes);
+            bytes.extend_from_slice(&payload);
+        }
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        #[cfg(unix)]
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+        self.file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        self.unsynced = 0;
+        self.log_len = 0;
+        self.file_len = bytes.len() as u64;
+        self.poisoned = false;
+        Ok(())
+    }
+}
+
+impl<K, V> DurableRBTreeMap<K, V> {
+    /// Returns the path of the log.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Returns the policy deciding when records are synced.
+    pub fn sync_policy(&self) -> SyncPolicy {
+        self.policy
+    }
+}
+
+impl<K, V> Deref for DurableRBTreeMap<K, V> {
+    type Target = RBTreeMap<K, V>;
+    fn deref(&self) -> &RBTreeMap<K, V> {
+        &self.map
+    }
+}
+
+impl<K, V> Drop for DurableRBTreeMap<K, V> {
+    fn drop(&mut self) {
+        if self.unsynced != 0 {
+            let _ = self.file.sync_data();
+        }
+    }
+}
@@ -0,0 +1,252 @@
+#[cfg(feature = "std")]
+extern crate std;
+use super::RBTreeMap;
+use core::{borrow::Borrow, ops::Add};
+
+/// A source of time for [`ExpiringMap`].
+///
+/// Any monotonic counter works, so the map can be driven by a hardware timer under
+/// `no_std`, or by a hand-advanced counter in deterministic tests.
+///
+/// # Examples
+///
+/// ```
+/// use core::cell::Cell;
+/// use xsl::collections::Clock;
+///
+/// struct Ticks(Cell<u64>);
+///
+/// impl Clock for Ticks {
+///     type Instant = u64;
+///     type Duration = u64;
+///     fn now(&self) -> u64 {
+///         self.0.get()
+///     }
+/// }
+/// ```
+pub trait Clock {
+    /// A point in time; later instants compare greater.
+    type Instant: Ord + Clone + Add<Self::Duration, Output = Self::Instant>;
+    /// A span of time that can be added to an [`Instant`](Clock::Instant).
+    type Duration;
+    /// Returns the current time.
+    fn now(&self) -> Self::Instant;
+}
+
+/// A [`Clock`] reading [`std::time::Instant::now`].
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    type Instant = std::time::Instant;
+    type Duration = std::time::Duration;
+    fn now(&self) -> std::time::Instant {
+        std::time::Instant::now()
+    }
+}
+
+/// A map whose entries expire after a per-entry time to live.
+///
+/// Entries live in an [`RBTreeMap`] keyed by `K`, and a second tree orders them by
+/// deadline, so expired entries can be purged without scanning the whole map.
+/// An entry is expired once the clock reaches its deadline. Expired entries are
+/// invisible to lookups but keep using memory until [`purge_expired`] removes them.
+///
+/// [`purge_expired`]: ExpiringMap::purge_expired
+///
+/// # Examples
+///
+/// ```
+/// use core::cell::Cell;
+/// use xsl::collections::{Clock, ExpiringMap};
+///
+/// struct Ticks<'a>(&'a Cell<u64>);
+///
+/// impl Clock for Ticks<'_> {
+///     type Instant = u64;
+///     type Duration = u64;
+///     fn now(&self) -> u64 {
+///         self.0.get()
+///     }
+/// }
+///
+/// let time = Cell::new(0);
+/// let mut cache = ExpiringMap::new(Ticks(&time));
+/// cache.insert_with_ttl("a", 1, 10);
+/// cache.insert_with_ttl("b", 2, 20);
+///
+/// time.set(15);
+/// assert_eq!(cache.get(&"a"), None);
+/// assert_eq!(cache.get(&"b"), Some(&2));
+/// assert_eq!(cache.purge_expired(15), 1);
+/// assert_eq!(cache.next_expiry(), Some(&20));
+/// ```
+pub struct ExpiringMap<K, V, C>
+where
+    C: Clock,
+{
+    entries: RBTreeMap<K, (V, C::Instant)>,
+    deadlines: RBTreeMap<(C::Instant, K), ()>,
+    clock: C,
+}
+
+impl<K, V, C> ExpiringMap<K, V, C>
+where
+    C: Clock,
+{
+    /// Makes a new, empty `ExpiringMap` reading time from `clock`.
+    pub fn new(clock: C) -> Self {
+        Self {
+            entries: RBTreeMap::new(),
+            deadlines: RBTreeMap::new(),
+            clock,
+        }
+    }
+    /// Returns a reference to the map's clock.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+    /// Returns the number of entries in the map, including expired entries
+    /// that have not been purged yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    /// Returns `true` if the map contains no entries, expired or not.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Removes every entry from the map.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.deadlines.clear();
+    }
+    /// Returns the earliest deadline in the map, or `None` if the map is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use xsl::collections::{Clock, ExpiringMap};
+    /// # struct Zero;
+    /// # impl Clock for Zero {
+    /// #     type Instant = u64;
+    /// #     type Duration = u64;
+    /// #     fn now(&self) -> u64 { 0 }
+    /// # }
+    /// let mut cache = ExpiringMap::new(Zero);
+    /// assert_eq!(cache.next_expiry(), None);
+    /// cache.insert_with_ttl(1, "a", 30);
+    /// cache.insert_with_ttl(2, "b", 10);
+    /// assert_eq!(cache.next_expiry(), Some(&10));
+    /// ```
+    pub fn next_expiry(&self) -> Option<&C::Instant> {
+        self.deadlines
+            .first_key_value()
+            .map(|((deadline, _), _)| deadline)
+    }
+}
+
+impl<K, V, C> ExpiringMap<K, V, C>
+where
+    K: Ord + Clone,
+    C: Clock,
+{
+    /// Inserts a key-value pair that expires `ttl` after the current time.
+    ///
+    /// If the map had a live entry for this key, its value is returned and its
+    /// deadline is replaced.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use xsl::collections::{Clock, ExpiringMap};
+    /// # struct Zero;
+    /// # impl Clock for Zero {
+    /// #     type Instant = u64;
+    /// #     type Duration = u64;
+    /// #     fn now(&self) -> u64 { 0 }
+    /// # }
+    /// let mut cache = ExpiringMap::new(Zero);
+    /// assert_eq!(cache.insert_with_ttl("a", 1, 5), None);
+    /// assert_eq!(cache.insert_with_ttl("a", 2, 8), Some(1));
+    /// assert_eq!(cache.next_expiry(), Some(&8));
+    /// ```
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: C::Duration) -> Option<V> {
+        let now = self.clock.now();
+        let deadline = now.clone() + ttl;
+        let old = self.entries.insert(key.clone(), (value, deadline.clone()));
+        // drop the old deadline first, as it may equal the new one
+        if let Some((_, old_deadline)) = &old {
+            self.deadlines.remove(&(old_deadline.clone(), key.clone()));
+        }
+        self.deadlines.insert((deadline, key), ());
+        let (old, old_deadline) = old?;
+        (old_deadline > now).then_some(old)
+    }
+    /// Returns a reference to the value corresponding to the key,
+    /// unless the entry is missing or expired.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (value, deadline) = self.entries.get(key)?;
+        (*deadline > self.clock.now()).then_some(value)
+    }
+    /// Returns a mutable reference to the value corresponding to the key,
+    /// unless the entry is missing or expired.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let now = self.clock.now();
+        let (value, deadline) = self.entries.get_mut(key)?;
+        (*deadline > now).then_some(value)
+    }
+    /// Returns `true` if the map contains a live entry for the key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get(key).is_some()
+    }
+    /// Returns the deadline of the entry for the key, even if it already passed.
+    pub fn deadline<Q>(&self, key: &Q) -> Option<&C::Instant>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.entries.get(key).map(|(_, deadline)| deadline)
+    }
+    /// Removes a key from the map, returning its value if the entry was live.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let (key, (value, deadline)) = self.entries.remove_entry(key)?;
+        let live = deadline > self.clock.now();
+        self.deadlines.remove(&(deadline, key));
+        live.then_some(value)
+    }
+    /// Removes every entry whose deadline is at or before `now`,
+    /// returning how many were removed.
+    ///
+    /// Each removed entry costs `O(log n)`; live entries are never visited.
+    pub fn purge_expired(&mut self, now: C::Instant) -> usize {
+        let mut purged = 0;
+        while self
+            .deadlines
+            .first_key_value()
+            .is_some_and(|((deadline, _), _)| *deadline <= now)
+        {
+            let ((_, key), ()) = self.deadlines.pop_first().unwrap();
+            self.entries.remove(&key);
+            purged += 1;
+        }
+        purged
+    }
+}
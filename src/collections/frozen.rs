@@ -0,0 +1,300 @@
+use super::RBTreeMap;
+use crate::alloc::{Allocator, Global, Vec};
+use core::{
+    borrow::Borrow,
+    fmt::Debug,
+    iter::FusedIterator,
+    ops::{Bound, Range, RangeBounds},
+};
+
+/// A read-only map laid out for fast lookups.
+///
+/// Entries are stored in one array in Eytzinger order: the root of an implicit
+/// balanced search tree first, then each level left to right. A search touches
+/// the array front to back and turns each comparison into index arithmetic
+/// rather than a jump, so it is branch-light and prefetch-friendly: the top
+/// levels stay in cache and the next probes are easy to predict. Whether the
+/// comparison itself compiles without a branch is up to the key type and the
+/// compiler. Built by [`RBTreeMap::freeze`], and turned back with
+/// [`thaw`](FrozenMap::thaw).
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::RBTreeMap;
+///
+/// let map = RBTreeMap::from([(1, "a"), (5, "e"), (3, "c")]);
+/// let frozen = map.freeze();
+/// assert_eq!(frozen.get(&3), Some(&"c"));
+/// assert_eq!(frozen.rank(&4), 2);
+/// assert!(frozen.range(2..).eq([(&3, &"c"), (&5, &"e")]));
+///
+/// let mut map = frozen.thaw();
+/// map.insert(2, "b");
+/// assert_eq!(map.len(), 4);
+/// ```
+#[derive(Clone)]
+pub struct FrozenMap<K, V> {
+    /// The keys in Eytzinger order, apart from the values so more fit in a cache line.
+    keys: Vec<K>,
+    /// The values in the same order as `keys`.
+    values: Vec<V>,
+    /// The position in `keys` of the entry with each rank.
+    by_rank: Vec<usize>,
+    /// The rank of the entry at each position of `keys`.
+    rank_of: Vec<usize>,
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Turns the map into a read-only [`FrozenMap`] with faster lookups.
+    ///
+    /// Takes `O(n)` time and frees every node of the map.
+    pub fn freeze(self) -> FrozenMap<K, V> {
+        let len = self.len();
+        let mut by_rank = Vec::with_capacity(len);
+        let mut stack = Vec::new();
+        let mut pos = 0;
+        // walk the implicit tree in order, numbering positions by rank
+        while pos < len || !stack.is_empty() {
+            while pos < len {
+                stack.push(pos);
+                pos = 2 * pos + 1;
+            }
+            let top = stack.pop().unwrap();
+            by_rank.push(top);
+            pos = 2 * top + 2;
+        }
+        let mut rank_of = Vec::with_capacity(len);
+        rank_of.resize(len, 0);
+        for (rank, &pos) in by_rank.iter().enumerate() {
+            rank_of[pos] = rank;
+        }
+        let mut sorted: Vec<Option<(K, V)>> = self.into_iter().map(Some).collect();
+        let mut keys = Vec::with_capacity(len);
+        let mut values = Vec::with_capacity(len);
+        for &rank in &rank_of {
+            let (k, v) = sorted[rank].take().unwrap();
+            keys.push(k);
+            values.push(v);
+        }
+        FrozenMap {
+            keys,
+            values,
+            by_rank,
+            rank_of,
+        }
+    }
+}
+
+impl<K, V> FrozenMap<K, V> {
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Gets an iterator over the entries of the map, sorted by key.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            map: self,
+            ranks: 0..self.len(),
+        }
+    }
+    /// Returns the entry whose key has `rank` smaller keys,
+    /// or `None` if `rank` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let frozen = RBTreeMap::from([(10, 'a'), (20, 'b'), (30, 'c')]).freeze();
+    /// assert_eq!(frozen.select(1), Some((&20, &'b')));
+    /// assert_eq!(frozen.select(3), None);
+    /// ```
+    pub fn select(&self, rank: usize) -> Option<(&K, &V)> {
+        let pos = *self.by_rank.get(rank)?;
+        Some((&self.keys[pos], &self.values[pos]))
+    }
+    /// Turns the map back into a mutable [`RBTreeMap`] in `O(n)` time.
+    pub fn thaw(self) -> RBTreeMap<K, V>
+    where
+        K: Ord,
+    {
+        let mut layout: Vec<Option<(K, V)>> =
+            self.keys.into_iter().zip(self.values).map(Some).collect();
+        let sorted = self.by_rank.iter().map(|&pos| layout[pos].take().unwrap());
+        RBTreeMap::bulk_build_from_sorted_iter(sorted, Global::default())
+    }
+    /// Returns the rank of the first entry whose key does not satisfy `go_right`,
+    /// which must hold for a prefix of the keys in order.
+    #[inline]
+    fn partition_point<F>(&self, go_right: F) -> usize
+    where
+        F: Fn(&K) -> bool,
+    {
+        let len = self.len();
+        let mut pos = 0;
+        while pos < len {
+            pos = 2 * pos + 1 + go_right(&self.keys[pos]) as usize;
+        }
+        // Undo the final run of right turns and the left turn before it.
+        let found = (pos + 1) >> ((pos + 1).trailing_ones() + 1);
+        match found {
+            0 => len,
+            found => self.rank_of[found - 1],
+        }
+    }
+}
+
+impl<K, V> FrozenMap<K, V>
+where
+    K: Ord,
+{
+    /// Returns the number of keys in the map that are less than `key`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let frozen = RBTreeMap::from([(10, 'a'), (20, 'b'), (30, 'c')]).freeze();
+    /// assert_eq!(frozen.rank(&5), 0);
+    /// assert_eq!(frozen.rank(&20), 1);
+    /// assert_eq!(frozen.rank(&25), 2);
+    /// ```
+    pub fn rank<Q>(&self, key: &Q) -> usize
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.partition_point(|k| k.borrow() < key)
+    }
+    /// Returns the key-value pair corresponding to the key.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.select(self.rank(key))
+            .filter(|(k, _)| (*k).borrow() == key)
+    }
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let frozen = RBTreeMap::from([(1, "a")]).freeze();
+    /// assert_eq!(frozen.get(&1), Some(&"a"));
+    /// assert_eq!(frozen.get(&2), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).is_some()
+    }
+    /// Gets an iterator over the entries whose keys lie in `range`, sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let frozen = RBTreeMap::from([(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')]).freeze();
+    /// assert!(frozen.range(2..4).eq([(&2, &'b'), (&3, &'c')]));
+    /// assert!(frozen.range(..=1).rev().eq([(&1, &'a')]));
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.partition_point(|k| k.borrow() < key),
+            Bound::Excluded(key) => self.partition_point(|k| k.borrow() <= key),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.partition_point(|k| k.borrow() <= key),
+            Bound::Excluded(key) => self.partition_point(|k| k.borrow() < key),
+            Bound::Unbounded => self.len(),
+        };
+        Iter {
+            map: self,
+            ranks: start..end.max(start),
+        }
+    }
+}
+
+impl<K, V> Debug for FrozenMap<K, V>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a FrozenMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+/// An iterator over entries of a [`FrozenMap`], sorted by key.
+pub struct Iter<'a, K, V> {
+    map: &'a FrozenMap<K, V>,
+    ranks: Range<usize>,
+}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            ranks: self.ranks.clone(),
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ranks.next().and_then(|rank| self.map.select(rank))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ranks.size_hint()
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ranks
+            .next_back()
+            .and_then(|rank| self.map.select(rank))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
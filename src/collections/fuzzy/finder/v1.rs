@@ -140,10 +140,6 @@ impl<T> Finder<T> {
                 valid_nodes.push(node);
             }
         }
-        over.iter()
-            .rev()
-            .map(|n| n.values.iter())
-            .flatten()
-            .collect()
+        over.iter().rev().flat_map(|n| n.values.iter()).collect()
     }
 }
@@ -0,0 +1,522 @@
+extern crate alloc;
+
+use super::{
+    rbtree_map::{Parts, Range},
+    RBTreeMap,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    any::TypeId,
+    fmt::Debug,
+    iter::FusedIterator,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+/// An ordered index over the records of a [`MultiIndexMap`].
+///
+/// Indexes are usually zero-sized marker types, named in
+/// [`with_index`](MultiIndexMap::with_index) and in lookups such as
+/// [`get_by`](MultiIndexMap::get_by).
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::multi_index::OrderedIndex;
+///
+/// struct User {
+///     id: u32,
+///     name: String,
+/// }
+///
+/// struct ById;
+///
+/// impl OrderedIndex<User> for ById {
+///     type Key = u32;
+///     const UNIQUE: bool = true;
+///     fn key(user: &User) -> u32 {
+///         user.id
+///     }
+/// }
+///
+/// struct ByName;
+///
+/// impl OrderedIndex<User> for ByName {
+///     type Key = String;
+///     fn key(user: &User) -> String {
+///         user.name.clone()
+///     }
+/// }
+/// ```
+pub trait OrderedIndex<T>: 'static {
+    /// The key the records are ordered by.
+    type Key: Ord;
+    /// Whether two records may share a key.
+    const UNIQUE: bool = false;
+    /// Extracts the key of `record`.
+    fn key(record: &T) -> Self::Key;
+}
+
+/// The tree of one index, over record slots. Ties between equal keys are
+/// broken by slot, so a key maps to a run of adjacent entries.
+struct IndexTree<T, I>
+where
+    I: OrderedIndex<T>,
+{
+    tree: RBTreeMap<(I::Key, usize), ()>,
+    /// The key of the record being modified, from before the change.
+    stash: Option<I::Key>,
+    _marker: PhantomData<fn(&T)>,
+}
+
+/// The operations [`MultiIndexMap`] applies to every index alike.
+trait AnyIndex<T> {
+    fn insert(&mut self, record: &T, slot: usize);
+    fn remove(&mut self, record: &T, slot: usize);
+    fn unique(&self) -> bool;
+    /// Returns `true` if a unique index already has `record`'s key under a
+    /// slot other than `slot`.
+    fn clashes(&self, record: &T, slot: usize) -> bool;
+    /// Remembers `record`'s key before it is modified.
+    fn stash(&mut self, record: &T);
+    /// Removes the entry for the stashed key.
+    fn remove_stashed(&mut self, slot: usize);
+    /// Forgets the stashed key, leaving the entry where it was.
+    fn unstash(&mut self);
+    /// Moves the entry from the stashed key to `record`'s key, if it changed.
+    fn reposition(&mut self, record: &T, slot: usize);
+    fn clear(&mut self);
+}
+
+impl<T, I> IndexTree<T, I>
+where
+    I: OrderedIndex<T>,
+{
+    fn new() -> Self {
+        Self {
+            tree: RBTreeMap::new(),
+            stash: None,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns the slots of the records with keys in `range`, in key order.
+    fn range<R>(&self, range: R) -> Range<'_, (I::Key, usize), ()>
+    where
+        R: RangeBounds<I::Key>,
+    {
+        // `Parts` compares equal to every entry of a key, so an included bound
+        // takes the whole run of equal keys and an excluded bound skips it
+        self.tree.range_by((
+            range.start_bound().map(|key| Parts((key,))),
+            range.end_bound().map(|key| Parts((key,))),
+        ))
+    }
+    fn first(&self, key: &I::Key) -> Option<usize> {
+        self.range((Bound::Included(key), Bound::Included(key)))
+            .next()
+            .map(|((_, slot), _)| *slot)
+    }
+}
+
+impl<T, I> AnyIndex<T> for IndexTree<T, I>
+where
+    I: OrderedIndex<T>,
+{
+    fn insert(&mut self, record: &T, slot: usize) {
+        self.tree.insert((I::key(record), slot), ());
+    }
+    fn remove(&mut self, record: &T, slot: usize) {
+        self.tree.remove(&(I::key(record), slot));
+    }
+    fn unique(&self) -> bool {
+        I::UNIQUE
+    }
+    fn clashes(&self, record: &T, slot: usize) -> bool {
+        if !I::UNIQUE {
+            return false;
+        }
+        let key = I::key(record);
+        self.range((Bound::Included(&key), Bound::Included(&key)))
+            .any(|((_, other), _)| *other != slot)
+    }
+    fn stash(&mut self, record: &T) {
+        self.stash = Some(I::key(record));
+    }
+    fn remove_stashed(&mut self, slot: usize) {
+        if let Some(key) = self.stash.take() {
+            self.tree.remove(&(key, slot));
+        }
+    }
+    fn unstash(&mut self) {
+        self.stash = None;
+    }
+    fn reposition(&mut self, record: &T, slot: usize) {
+        let Some(old) = self.stash.take() else {
+            return;
+        };
+        let new = I::key(record);
+        if new != old {
+            self.tree.remove(&(old, slot));
+            self.tree.insert((new, slot), ());
+        }
+    }
+    fn clear(&mut self) {
+        self.tree.clear();
+    }
+}
+
+/// A collection of records searchable by several keys at once, each through
+/// its own red-black tree.
+///
+/// The records live once, in slots; every index declared with
+/// [`with_index`](Self::with_index) is a tree from its key to slots, kept in
+/// step by every insertion, removal and [`modify_by`](Self::modify_by). An
+/// index is named by its [`OrderedIndex`] type, as in `get_by::<ById>(&7)`.
+/// A [unique](OrderedIndex::UNIQUE) index refuses a second record with the
+/// same key; other indexes hold any number of records with equal keys.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::multi_index::OrderedIndex;
+/// use xsl::collections::MultiIndexMap;
+///
+/// #[derive(Debug, Clone, PartialEq)]
+/// struct Event {
+///     id: u32,
+///     time: u64,
+///     name: &'static str,
+/// }
+///
+/// struct ById;
+/// impl OrderedIndex<Event> for ById {
+///     type Key = u32;
+///     const UNIQUE: bool = true;
+///     fn key(event: &Event) -> u32 {
+///         event.id
+///     }
+/// }
+///
+/// struct ByTime;
+/// impl OrderedIndex<Event> for ByTime {
+///     type Key = u64;
+///     fn key(event: &Event) -> u64 {
+///         event.time
+///     }
+/// }
+///
+/// let mut events = MultiIndexMap::new()
+///     .with_index::<ById>()
+///     .with_index::<ByTime>();
+/// events.insert(Event { id: 1, time: 30, name: "c" }).unwrap();
+/// events.insert(Event { id: 2, time: 10, name: "a" }).unwrap();
+/// events.insert(Event { id: 3, time: 20, name: "b" }).unwrap();
+/// // a second record with id 2 is handed back
+/// assert!(events.insert(Event { id: 2, time: 0, name: "x" }).is_err());
+///
+/// assert_eq!(events.get_by::<ById>(&3).unwrap().name, "b");
+/// let names: Vec<_> = events.range_by::<ByTime, _>(15..).map(|e| e.name).collect();
+/// assert_eq!(names, ["b", "c"]);
+///
+/// // moving an event in time moves it in the time index only
+/// events.modify_by::<ById, _>(&1, |e| e.time = 0).unwrap();
+/// let names: Vec<_> = events.iter_by::<ByTime>().map(|e| e.name).collect();
+/// assert_eq!(names, ["c", "a", "b"]);
+/// ```
+pub struct MultiIndexMap<T> {
+    records: Vec<Option<T>>,
+    /// Empty slots, reused before the storage grows.
+    free: Vec<usize>,
+    indexes: Vec<(TypeId, Box<dyn AnyIndex<T>>)>,
+    length: usize,
+}
+
+impl<T> Default for MultiIndexMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for MultiIndexMap<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T> MultiIndexMap<T> {
+    /// Makes a new `MultiIndexMap` without records or indexes.
+    pub const fn new() -> Self {
+        Self {
+            records: Vec::new(),
+            free: Vec::new(),
+            indexes: Vec::new(),
+            length: 0,
+        }
+    }
+    /// Adds the index `I`, over the records already present. Indexes are
+    /// looked up by type, so indexed records must be `'static`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `I` is already an index, or if it is unique and two present
+    /// records share a key.
+    pub fn with_index<I>(mut self) -> Self
+    where
+        T: 'static,
+        I: OrderedIndex<T>,
+    {
+        let id = TypeId::of::<I>();
+        assert!(
+            self.indexes.iter().all(|(other, _)| *other != id),
+            "index declared twice"
+        );
+        let mut index = IndexTree::<T, I>::new();
+        for (slot, record) in self.slots() {
+            assert!(
+                !index.clashes(record, slot),
+                "duplicate key in unique index"
+            );
+            index.insert(record, slot);
+        }
+        self.indexes.push((id, Box::new(index)));
+        self
+    }
+    /// Returns the number of records.
+    pub fn len(&self) -> usize {
+        self.length
+    }
+    /// Returns `true` if there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+    /// Removes every record, keeping the indexes.
+    pub fn clear(&mut self) {
+        self.records.clear();
+        self.free.clear();
+        self.indexes.iter_mut().for_each(|(_, index)| index.clear());
+        self.length = 0;
+    }
+    /// Gets an iterator over the records, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.slots().map(|(_, record)| record)
+    }
+    fn slots(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        self.records
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, record)| Some((slot, record.as_ref()?)))
+    }
+    fn index<I>(&self) -> &IndexTree<T, I>
+    where
+        I: OrderedIndex<T>,
+    {
+        let id = TypeId::of::<I>();
+        let Some((_, index)) = self.indexes.iter().find(|(other, _)| *other == id) else {
+            panic!("no such index: {}", core::any::type_name::<I>());
+        };
+        // the index stored under the type id of `I` is an `IndexTree<T, I>`
+        unsafe { &*(&**index as *const dyn AnyIndex<T> as *const IndexTree<T, I>) }
+    }
+    fn record(&self, slot: usize) -> &T {
+        self.records[slot].as_ref().unwrap()
+    }
+    /// Inserts a record into every index.
+    ///
+    /// If a unique index already holds a record with the same key, nothing is
+    /// inserted and the record is handed back as `Err(record)`.
+    pub fn insert(&mut self, record: T) -> Result<(), T> {
+        let slot = self.free.last().copied().unwrap_or(self.records.len());
+        if self
+            .indexes
+            .iter()
+            .any(|(_, index)| index.clashes(&record, slot))
+        {
+            return Err(record);
+        }
+        for (_, index) in self.indexes.iter_mut() {
+            index.insert(&record, slot);
+        }
+        match self.free.pop() {
+            Some(slot) => self.records[slot] = Some(record),
+            None => self.records.push(Some(record)),
+        }
+        self.length += 1;
+        Ok(())
+    }
+    /// Returns the first record, in the order of index `I`, whose key is `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `I` is not an index of the map, as do the other `_by` methods.
+    pub fn get_by<I>(&self, key: &I::Key) -> Option<&T>
+    where
+        I: OrderedIndex<T>,
+    {
+        Some(self.record(self.index::<I>().first(key)?))
+    }
+    /// Gets a double-ended iterator over the records whose keys in index `I`
+    /// lie in `range`, in the order of that index.
+    pub fn range_by<I, R>(&self, range: R) -> IndexRange<'_, T, I>
+    where
+        I: OrderedIndex<T>,
+        R: RangeBounds<I::Key>,
+    {
+        IndexRange {
+            range: self.index::<I>().range(range),
+            records: &self.records,
+        }
+    }
+    /// Gets an iterator over every record in the order of index `I`.
+    pub fn iter_by<I>(&self) -> IndexRange<'_, T, I>
+    where
+        I: OrderedIndex<T>,
+    {
+        self.range_by::<I, _>(..)
+    }
+    /// Removes the first record, in the order of index `I`, whose key is `key`,
+    /// from every index, and returns it.
+    pub fn remove_by<I>(&mut self, key: &I::Key) -> Option<T>
+    where
+        I: OrderedIndex<T>,
+    {
+        let slot = self.index::<I>().first(key)?;
+        let record = self.records[slot].take().unwrap();
+        for (_, index) in self.indexes.iter_mut() {
+            index.remove(&record, slot);
+        }
+        self.free.push(slot);
+        self.length -= 1;
+        Some(record)
+    }
+    /// Calls `f` on the first record, in the order of index `I`, whose key is
+    /// `key`, then moves it in every index whose key for it changed.
+    ///
+    /// Returns `Ok(true)` if a record was modified and `Ok(false)` if there is
+    /// none with that key.
+    ///
+    /// If the new keys clash with another record in a unique index, the change
+    /// is undone: the record is put back as it was before `f`, in the same place
+    /// in every index, and the modified copy is handed back as `Err(record)`.
+    /// To have the original to put back, the record is cloned first whenever
+    /// the map has a unique index.
+    ///
+    /// If `f` panics, the half-modified record is removed from the map and
+    /// dropped, so the indexes never hold keys it no longer has.
+    pub fn modify_by<I, F>(&mut self, key: &I::Key, f: F) -> Result<bool, T>
+    where
+        T: Clone,
+        I: OrderedIndex<T>,
+        F: FnOnce(&mut T),
+    {
+        let Some(slot) = self.index::<I>().first(key) else {
+            return Ok(false);
+        };
+        let record = self.records[slot].as_mut().unwrap();
+        for (_, index) in self.indexes.iter_mut() {
+            index.stash(record);
+        }
+        // only a unique index can refuse the change
+        let original = self
+            .indexes
+            .iter()
+            .any(|(_, index)| index.unique())
+            .then(|| record.clone());
+        let guard = DiscardOnUnwind { map: self, slot };
+        f(guard.map.records[slot].as_mut().unwrap());
+        let clash = guard
+            .map
+            .indexes
+            .iter()
+            .any(|(_, index)| index.clashes(guard.map.records[slot].as_ref().unwrap(), slot));
+        core::mem::forget(guard);
+        if let (true, Some(original)) = (clash, original) {
+            for (_, index) in self.indexes.iter_mut() {
+                index.unstash();
+            }
+            let record = self.records[slot].as_mut().unwrap();
+            return Err(core::mem::replace(record, original));
+        }
+        let record = self.records[slot].as_ref().unwrap();
+        for (_, index) in self.indexes.iter_mut() {
+            index.reposition(record, slot);
+        }
+        Ok(true)
+    }
+    /// Takes the record in `slot` out of the map, finding it in each index by
+    /// the key stashed before it was modified.
+    fn discard_stashed(&mut self, slot: usize) -> T {
+        let record = self.records[slot].take().unwrap();
+        for (_, index) in self.indexes.iter_mut() {
+            index.remove_stashed(slot);
+        }
+        self.free.push(slot);
+        self.length -= 1;
+        record
+    }
+}
+
+/// Discards the record being modified by [`MultiIndexMap::modify_by`] if the
+/// caller's closure panics, as its keys may no longer match the stashed ones.
+struct DiscardOnUnwind<'a, T> {
+    map: &'a mut MultiIndexMap<T>,
+    slot: usize,
+}
+
+impl<T> Drop for DiscardOnUnwind<'_, T> {
+    fn drop(&mut self) {
+        drop(self.map.discard_stashed(self.slot));
+    }
+}
+
+/// An iterator over the records of a [`MultiIndexMap`] in the order of index `I`.
+///
+/// This `struct` is created by [`MultiIndexMap::range_by`] and
+/// [`MultiIndexMap::iter_by`].
+pub struct IndexRange<'a, T, I>
+where
+    I: OrderedIndex<T>,
+{
+    range: Range<'a, (I::Key, usize), ()>,
+    records: &'a [Option<T>],
+}
+
+impl<T, I> Clone for IndexRange<'_, T, I>
+where
+    I: OrderedIndex<T>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            range: self.range.clone(),
+            records: self.records,
+        }
+    }
+}
+
+impl<'a, T, I> Iterator for IndexRange<'a, T, I>
+where
+    I: OrderedIndex<T>,
+{
+    type Item = &'a T;
+    fn next(&mut self) -> Option<&'a T> {
+        let ((_, slot), _) = self.range.next()?;
+        self.records[*slot].as_ref()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<T, I> DoubleEndedIterator for IndexRange<'_, T, I>
+where
+    I: OrderedIndex<T>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let ((_, slot), _) = self.range.next_back()?;
+        self.records[*slot].as_ref()
+    }
+}
+
+impl<T, I> FusedIterator for IndexRange<'_, T, I> where I: OrderedIndex<T> {}
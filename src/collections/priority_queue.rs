@@ -0,0 +1,220 @@
+use super::rbtree::{Entry, Handle};
+use super::RBTreeMap;
+use core::borrow::Borrow;
+
+/// A min-priority queue whose entries are addressed by id.
+///
+/// Entries are kept in a red-black tree ordered by `(priority, insertion order)`,
+/// next to an index from id to that position, so every operation is `O(log n)`.
+/// Entries with equal priorities are popped in the order they were pushed.
+///
+/// The two trees point at each other's nodes through [`Handle`]s, so each id is
+/// stored once, and an operation searches the index at most once and the queue
+/// only to insert.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::IndexedPriorityQueue;
+///
+/// let mut queue = IndexedPriorityQueue::new();
+/// queue.push("a", 3);
+/// queue.push("b", 1);
+/// queue.push("c", 2);
+/// queue.change_priority(&"a", 0);
+///
+/// assert_eq!(queue.pop_min(), Some(("a", 0)));
+/// assert_eq!(queue.pop_min(), Some(("b", 1)));
+/// assert_eq!(queue.pop_min(), Some(("c", 2)));
+/// assert_eq!(queue.pop_min(), None);
+/// ```
+pub struct IndexedPriorityQueue<Id, P> {
+    /// Maps each position to the handle of its id in `index`.
+    queue: RBTreeMap<(P, u64), Handle>,
+    /// Maps each id to the handle of its position in `queue`.
+    index: RBTreeMap<Id, Handle>,
+    sequence: u64,
+}
+
+impl<Id, P> Default for IndexedPriorityQueue<Id, P> {
+    fn default() -> Self {
+        Self {
+            queue: RBTreeMap::new(),
+            index: RBTreeMap::new(),
+            sequence: 0,
+        }
+    }
+}
+
+impl<Id, P> IndexedPriorityQueue<Id, P> {
+    /// Makes a new, empty `IndexedPriorityQueue`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Returns the number of entries in the queue.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+    /// Returns `true` if the queue contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+    /// Removes every entry from the queue.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.index.clear();
+    }
+    /// Returns the entry with the lowest priority without removing it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::IndexedPriorityQueue;
+    ///
+    /// let mut queue = IndexedPriorityQueue::new();
+    /// assert_eq!(queue.peek(), None);
+    /// queue.push(1, "b");
+    /// queue.push(2, "a");
+    /// assert_eq!(queue.peek(), Some((&2, &"a")));
+    /// ```
+    pub fn peek(&self) -> Option<(&Id, &P)> {
+        let ((priority, _), &handle) = self.queue.first_key_value()?;
+        let (id, _) = self.index.get_by_handle(handle).unwrap();
+        Some((id, priority))
+    }
+}
+
+impl<Id, P> IndexedPriorityQueue<Id, P>
+where
+    Id: Ord,
+    P: Ord,
+{
+    /// Pushes `id` with the given priority.
+    ///
+    /// If `id` is already queued, its priority is replaced as with
+    /// [`change_priority`](Self::change_priority) and the old priority is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::IndexedPriorityQueue;
+    ///
+    /// let mut queue = IndexedPriorityQueue::new();
+    /// assert_eq!(queue.push("job", 5), None);
+    /// assert_eq!(queue.push("job", 2), Some(5));
+    /// assert_eq!(queue.len(), 1);
+    /// ```
+    pub fn push(&mut self, id: Id, priority: P) -> Option<P> {
+        let entry = match self.index.entry(id) {
+            Entry::Occupied(entry) => {
+                let position = entry.into_mut();
+                return Some(Self::requeue(&mut self.queue, position, priority));
+            }
+            Entry::Vacant(entry) => entry.insert_with_handle(Handle::DANGLING),
+        };
+        let position = Self::enqueue(&mut self.queue, (priority, self.sequence), entry);
+        self.sequence += 1;
+        *self.index.get_mut_by_handle(entry).unwrap().1 = position;
+        None
+    }
+    /// Removes the entry with the lowest priority and returns it.
+    ///
+    /// Among entries with equal priorities the one pushed first is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::IndexedPriorityQueue;
+    ///
+    /// let mut queue = IndexedPriorityQueue::new();
+    /// queue.push('x', 1);
+    /// queue.push('y', 1);
+    /// assert_eq!(queue.pop_min(), Some(('x', 1)));
+    /// assert_eq!(queue.pop_min(), Some(('y', 1)));
+    /// assert_eq!(queue.pop_min(), None);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<(Id, P)> {
+        let ((priority, _), entry) = self.queue.pop_first()?;
+        let (id, _) = self.index.remove_by_handle(entry).unwrap();
+        Some((id, priority))
+    }
+    /// Changes the priority of `id`, returning the old priority,
+    /// or `None` if `id` is not queued.
+    ///
+    /// The entry keeps its place in the insertion order for breaking ties.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::IndexedPriorityQueue;
+    ///
+    /// let mut queue = IndexedPriorityQueue::new();
+    /// queue.push("a", 1);
+    /// queue.push("b", 2);
+    /// assert_eq!(queue.change_priority(&"b", 0), Some(2));
+    /// assert_eq!(queue.change_priority(&"c", 0), None);
+    /// assert_eq!(queue.peek(), Some((&"b", &0)));
+    /// ```
+    pub fn change_priority<Q>(&mut self, id: &Q, priority: P) -> Option<P>
+    where
+        Id: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let position = self.index.get_mut(id)?;
+        Some(Self::requeue(&mut self.queue, position, priority))
+    }
+    /// Removes `id` from the queue, returning its priority if it was queued.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::IndexedPriorityQueue;
+    ///
+    /// let mut queue = IndexedPriorityQueue::new();
+    /// queue.push("a", 1);
+    /// assert_eq!(queue.remove(&"a"), Some(1));
+    /// assert_eq!(queue.remove(&"a"), None);
+    /// assert!(queue.is_empty());
+    /// ```
+    pub fn remove<Q>(&mut self, id: &Q) -> Option<P>
+    where
+        Id: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let position = self.index.remove(id)?;
+        let ((priority, _), _) = self.queue.remove_by_handle(position).unwrap();
+        Some(priority)
+    }
+    /// Returns `true` if `id` is queued.
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.index.contains_key(id)
+    }
+    /// Returns the priority of `id`, or `None` if it is not queued.
+    pub fn priority<Q>(&self, id: &Q) -> Option<&P>
+    where
+        Id: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let &position = self.index.get(id)?;
+        let ((priority, _), _) = self.queue.get_by_handle(position).unwrap();
+        Some(priority)
+    }
+    /// Moves the entry at `position` to `priority`, keeping its insertion order,
+    /// and returns the old priority.
+    fn requeue(queue: &mut RBTreeMap<(P, u64), Handle>, position: &mut Handle, priority: P) -> P {
+        let ((old, sequence), entry) = queue.remove_by_handle(*position).unwrap();
+        *position = Self::enqueue(queue, (priority, sequence), entry);
+        old
+    }
+    /// Inserts `key`, which is not queued yet, and returns its handle.
+    fn enqueue(queue: &mut RBTreeMap<(P, u64), Handle>, key: (P, u64), entry: Handle) -> Handle {
+        let Entry::Vacant(vacant) = queue.entry(key) else {
+            unreachable!("insertion orders are unique");
+        };
+        vacant.insert_with_handle(entry)
+    }
+}
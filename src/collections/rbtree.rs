@@ -1,8 +1,26 @@
+mod comparable;
 mod entry;
 mod flag;
+mod handle;
 mod iter;
 mod map;
+mod multiset;
 mod node;
+mod observer;
+mod prefix;
+mod stats;
+mod transaction;
+mod validate;
 
+pub use comparable::{Comparable, Parts};
 pub use entry::{Entry, OccupiedEntry, VacantEntry};
-pub use map::RBTreeMap;
+pub use handle::Handle;
+pub use iter::{IntoIter, Range};
+pub(crate) use iter::{Iter, IterMut};
+pub use map::{Change, Diff, MergeJoin, MergeJoinItem, RBTreeMap};
+pub use multiset::{MultiSetIter, RBTreeMultiSet};
+pub use observer::Observer;
+pub use prefix::Prefix;
+pub use stats::TreeStats;
+pub use transaction::Transaction;
+pub use validate::InvariantViolation;
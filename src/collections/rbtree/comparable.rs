@@ -0,0 +1,97 @@
+use core::{borrow::Borrow, cmp::Ordering};
+
+/// Types that can be compared with keys of type `K` to look them up in an
+/// [`RBTreeMap`](super::RBTreeMap).
+///
+/// The lookups ending in `_by`, such as [`get_by`](super::RBTreeMap::get_by)
+/// and [`range_by`](super::RBTreeMap::range_by), take a `Comparable` key, while
+/// [`get`](super::RBTreeMap::get) and the others keep the `K: Borrow<Q>` bound
+/// of `BTreeMap`. Every `Q` that `K` borrows as is comparable through a blanket
+/// impl, so `&str` finds `String` keys either way. Implement it for your own
+/// query types to look up keys that cannot be borrowed as them, such as a
+/// composite key with owned parts. The ordering *must* match the ordering on `K`.
+///
+/// # Examples
+///
+/// ```
+/// use core::cmp::Ordering;
+/// use xsl::collections::rbtree_map::Comparable;
+/// use xsl::collections::RBTreeMap;
+///
+/// struct Name<'a>(&'a str);
+///
+/// impl Comparable<(String, u32)> for Name<'_> {
+///     fn compare(&self, key: &(String, u32)) -> Ordering {
+///         self.0.cmp(&key.0).then(Ordering::Greater)
+///     }
+/// }
+///
+/// let map = RBTreeMap::from([(("ann".to_string(), 1), 'a'), (("bob".to_string(), 2), 'b')]);
+/// // the first key after every ("ann", _)
+/// assert_eq!(map.range_by(Name("ann")..).next(), Some((&("bob".to_string(), 2), &'b')));
+/// ```
+pub trait Comparable<K: ?Sized> {
+    /// Compares `self` with `key`.
+    fn compare(&self, key: &K) -> Ordering;
+}
+
+impl<Q, K> Comparable<K> for Q
+where
+    Q: ?Sized + Ord,
+    K: ?Sized + Borrow<Q>,
+{
+    #[inline]
+    fn compare(&self, key: &K) -> Ordering {
+        self.cmp(key.borrow())
+    }
+}
+
+/// Borrowed leading parts of a tuple key, compared part by part with
+/// [`Comparable`].
+///
+/// With every part given, `Parts` finds a key like `(String, u64)` from
+/// `("name", &7)` without building an owned key. With fewer parts it compares
+/// equal to every key starting with them, so a range over it covers them all.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::rbtree_map::Parts;
+/// use xsl::collections::RBTreeMap;
+///
+/// let mut map = RBTreeMap::new();
+/// map.insert(("a".to_string(), 1u64), 'x');
+/// map.insert(("a".to_string(), 2), 'y');
+/// map.insert(("b".to_string(), 1), 'z');
+///
+/// assert_eq!(map.get_by(&Parts(("a", &2))), Some(&'y'));
+/// assert_eq!(map.remove_by(&Parts(("b", &1))), Some('z'));
+/// let values: Vec<_> = map.range_by(Parts(("a",))..=Parts(("a",))).map(|(_, v)| *v).collect();
+/// assert_eq!(values, ['x', 'y']);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Parts<T>(pub T);
+
+macro_rules! impl_parts {
+    ([$($key:ident $query:ident $index:tt),*] [$($rest:ident),*]) => {
+        impl<$($key,)* $($rest,)* $($query),*> Comparable<($($key,)* $($rest,)*)>
+            for Parts<($(&$query,)*)>
+        where
+            $($query: ?Sized + Comparable<$key>,)*
+        {
+            fn compare(&self, key: &($($key,)* $($rest,)*)) -> Ordering {
+                Ordering::Equal $(.then_with(|| self.0.$index.compare(&key.$index)))*
+            }
+        }
+    };
+}
+
+impl_parts!([K0 Q0 0] [K1]);
+impl_parts!([K0 Q0 0, K1 Q1 1] []);
+impl_parts!([K0 Q0 0] [K1, K2]);
+impl_parts!([K0 Q0 0, K1 Q1 1] [K2]);
+impl_parts!([K0 Q0 0, K1 Q1 1, K2 Q2 2] []);
+impl_parts!([K0 Q0 0] [K1, K2, K3]);
+impl_parts!([K0 Q0 0, K1 Q1 1] [K2, K3]);
+impl_parts!([K0 Q0 0, K1 Q1 1, K2 Q2 2] [K3]);
+impl_parts!([K0 Q0 0, K1 Q1 1, K2 Q2 2, K3 Q3 3] []);
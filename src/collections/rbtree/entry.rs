@@ -1,3 +1,5 @@
+use super::flag::{LEFT, RIGHT};
+use super::handle::Handle;
 use super::map::NdNotFound;
 use super::map::RBTreeMap;
 use super::node::OwnedNodeRef;
@@ -167,6 +169,10 @@ where
     pub(super) fn new(node: OwnedNodeRef<K, V>, tree: &'a mut RBTreeMap<K, V, A>) -> Self {
         OccupiedEntry { node, tree }
     }
+    /// Returns the node of the entry.
+    pub(super) fn node(&self) -> OwnedNodeRef<K, V> {
+        self.node.clone()
+    }
     /// Gets a reference to the key in the entry.
     ///
     /// # Examples
@@ -204,7 +210,9 @@ where
     /// ```
     #[inline]
     pub fn remove_entry(self) -> (K, V) {
-        self.tree.raw_remove(self.node)
+        let kv = self.tree.raw_remove(self.node);
+        self.tree.paranoid_shape();
+        kv
     }
     /// Gets a reference to the value in the entry.
     ///
@@ -261,6 +269,7 @@ where
     /// ```
     #[inline]
     pub fn get_mut(&mut self) -> &mut V {
+        self.tree.hook_get_mut(&self.node);
         &mut self.node.key_value.1
     }
 
@@ -292,6 +301,7 @@ where
     /// ```
     #[inline]
     pub fn into_mut(self) -> &'a mut V {
+        self.tree.hook_get_mut(&self.node);
         &mut self.node.into_mut().key_value.1
     }
 
@@ -318,8 +328,27 @@ where
     #[inline]
     pub fn insert(&mut self, mut value: V) -> V {
         core::mem::swap(&mut self.node.key_value.1, &mut value);
+        self.tree
+            .hooks
+            .updated(&self.node.key_value.0, &value, &self.node.key_value.1);
         value
     }
+    /// Returns a [`Handle`] to the entry, for finding it again without a search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Entry;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// let Entry::Occupied(mut entry) = map.entry(2) else { unreachable!() };
+    /// let handle = entry.handle();
+    /// assert_eq!(map.get_by_handle(handle), Some((&2, &"b")));
+    /// ```
+    pub fn handle(&mut self) -> Handle {
+        self.tree.handles.issue(self.node.clone(), &self.tree.alloc)
+    }
 }
 
 pub struct VacantEntry<'a, K, V, A>
@@ -379,6 +408,35 @@ where
     /// assert_eq!(map["poneyland"], 37);
     /// ```
     pub fn insert(self, value: V) -> &'a mut V
+    where
+        K: Ord,
+    {
+        &mut self.insert_node(value).into_mut().key_value.1
+    }
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a
+    /// [`Handle`] for finding the entry again without a search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Entry;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// let Entry::Vacant(entry) = map.entry("poneyland") else { unreachable!() };
+    /// let handle = entry.insert_with_handle(37);
+    /// assert_eq!(map.get_by_handle(handle), Some((&"poneyland", &37)));
+    /// ```
+    pub fn insert_with_handle(self, value: V) -> Handle
+    where
+        K: Ord,
+    {
+        let VacantEntry { key, nd, tree } = self;
+        let node = VacantEntry::new(key, nd, &mut *tree).insert_node(value);
+        tree.handles.issue(node, &tree.alloc)
+    }
+    /// Inserts the entry and returns its node, which becomes the map's finger.
+    pub(super) fn insert_node(self, value: V) -> OwnedNodeRef<K, V>
     where
         K: Ord,
     {
@@ -395,22 +453,35 @@ where
         unsafe {
             core::ptr::write(&mut node_ref.key_value, (self.key, value));
         }
+        self.tree
+            .hooks
+            .inserted(&node_ref.key_value.0, &node_ref.key_value.1);
+        self.tree.finger = node_ref.get_node_ref();
         match self.nd {
             NdNotFound::Root => {
                 self.tree.root = node_ref.get_node_ref();
+                self.tree.first = node_ref.get_node_ref();
+                self.tree.last = node_ref.get_node_ref();
                 self.tree.length += 1;
                 node_ref.flag.set_root();
-                &mut node_ref.into_mut().key_value.1
+                self.tree.paranoid();
+                node_ref
             }
             NdNotFound::Normal(mut parent, rela) => {
                 parent.set_child(node_ref.clone(), rela);
+                if self.tree.first.ptr == Some(parent.ptr) && rela == LEFT {
+                    self.tree.first = node_ref.get_node_ref();
+                } else if self.tree.last.ptr == Some(parent.ptr) && rela == RIGHT {
+                    self.tree.last = node_ref.get_node_ref();
+                }
                 self.tree.length += 1;
                 if parent.flag.is_red() {
-                    if let Some(new_root) = node_ref.double_red_adjust() {
+                    if let Some(new_root) = node_ref.double_red_adjust(&mut self.tree.counters) {
                         self.tree.root = new_root.get_node_ref();
                     }
                 }
-                &mut node_ref.into_mut().key_value.1
+                self.tree.paranoid();
+                node_ref
             }
         }
     }
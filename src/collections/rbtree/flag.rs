@@ -4,6 +4,7 @@ use core::fmt::Display;
 pub struct Flag {
     pub flag: u8,
 }
+#[allow(dead_code, clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, PartialEq)]
 pub enum Rela {
     LEFT,
@@ -23,14 +24,14 @@ impl From<u8> for Rela {
         }
     }
 }
-impl Into<u8> for Rela {
-    fn into(self) -> u8 {
-        self as u8
+impl From<Rela> for u8 {
+    fn from(rela: Rela) -> Self {
+        rela as u8
     }
 }
-impl Into<usize> for Rela {
-    fn into(self) -> usize {
-        self as usize
+impl From<Rela> for usize {
+    fn from(rela: Rela) -> Self {
+        rela as usize
     }
 }
 impl Display for Rela {
@@ -42,6 +43,7 @@ impl Display for Rela {
         }
     }
 }
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, PartialEq)]
 pub enum Color {
     RED,
@@ -66,9 +68,9 @@ impl From<u8> for Color {
         }
     }
 }
-impl Into<u8> for Color {
-    fn into(self) -> u8 {
-        (self as u8) << 2
+impl From<Color> for u8 {
+    fn from(color: Color) -> Self {
+        (color as u8) << 2
     }
 }
 impl Display for Color {
@@ -127,7 +129,7 @@ impl Flag {
     }
     #[inline(always)]
     pub fn clear_root(&mut self) -> &mut Self {
-        self.flag = self.flag & &!ROOT;
+        self.flag &= !ROOT;
         self
     }
     #[inline(always)]
@@ -0,0 +1,255 @@
+use super::{
+    map::RBTreeMap,
+    node::{NodeRef, OwnedNodeRef},
+};
+use crate::alloc::{Allocator, Box, Vec};
+use core::mem::size_of;
+
+/// A checked reference to an entry of an [`RBTreeMap`], which finds the entry
+/// again without a search.
+///
+/// Handles come from [`VacantEntry::insert_with_handle`] and
+/// [`OccupiedEntry::handle`]. Each carries a generation from a counter kept
+/// with the map's handle table, and the table's address, so a handle whose
+/// entry has been removed, or which belongs to another live map, is rejected
+/// rather than followed.
+///
+/// [`VacantEntry::insert_with_handle`]: super::VacantEntry::insert_with_handle
+/// [`OccupiedEntry::handle`]: super::OccupiedEntry::handle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    table: usize,
+    slot: u32,
+    generation: usize,
+}
+
+impl Handle {
+    /// A handle that no entry has, for filling a field before the real handle
+    /// is known.
+    pub(crate) const DANGLING: Handle = Handle {
+        table: 0,
+        slot: 0,
+        generation: 0,
+    };
+}
+
+struct Slot<K, V> {
+    node: NodeRef<K, V>,
+    /// Zero while the slot is free.
+    generation: usize,
+}
+
+/// The slots of the map's live handles, allocated from the map's allocator
+/// when the first handle is issued and kept until the map is dropped, so its
+/// address tells the map's handles apart and its generations never repeat.
+///
+/// Nodes do not record their slot. The table finds it by the node's address,
+/// so a map that never issues a handle pays for nothing but this pointer.
+pub(super) struct Handles<K, V, A: Allocator + Clone>(Option<Box<Table<K, V, A>, A>>);
+
+struct Table<K, V, A: Allocator + Clone> {
+    slots: Vec<Slot<K, V>, A>,
+    free: Vec<u32, A>,
+    /// The slot of each node with a handle, by the node's address.
+    index: RBTreeMap<usize, u32, A>,
+    /// The generation the next handle gets.
+    generation: usize,
+}
+
+impl<K, V, A: Allocator + Clone> Table<K, V, A> {
+    fn id(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+fn address<K, V>(node: &OwnedNodeRef<K, V>) -> usize {
+    node.ptr.as_ptr() as usize
+}
+
+impl<K, V, A: Allocator + Clone> Handles<K, V, A> {
+    pub(super) const fn new() -> Self {
+        Self(None)
+    }
+    /// Returns `true` if no node has a handle.
+    #[inline(always)]
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.as_ref().is_none_or(|table| table.index.is_empty())
+    }
+    /// Returns the bytes the table takes from the map's allocator.
+    pub(super) fn allocated_bytes(&self) -> usize {
+        self.0.as_ref().map_or(0, |table| {
+            size_of::<Table<K, V, A>>()
+                + table.slots.capacity() * size_of::<Slot<K, V>>()
+                + table.free.capacity() * size_of::<u32>()
+                + table.index.allocated_bytes()
+        })
+    }
+    /// Returns the handle of `node`, giving it one if it has none yet.
+    pub(super) fn issue(&mut self, node: OwnedNodeRef<K, V>, alloc: &A) -> Handle {
+        let table = self.0.get_or_insert_with(|| {
+            Box::new_in(
+                Table {
+                    slots: Vec::new_in(alloc.clone()),
+                    free: Vec::new_in(alloc.clone()),
+                    index: RBTreeMap::new_in(alloc.clone()),
+                    generation: 1,
+                },
+                alloc.clone(),
+            )
+        });
+        if let Some(&index) = table.index.get(&address(&node)) {
+            return Handle {
+                table: table.id(),
+                slot: index + 1,
+                generation: table.slots[index as usize].generation,
+            };
+        }
+        let generation = table.generation;
+        table.generation += 1;
+        let slot = Slot {
+            node: node.get_node_ref(),
+            generation,
+        };
+        let index = match table.free.pop() {
+            Some(index) => {
+                table.slots[index as usize] = slot;
+                index
+            }
+            None => {
+                table.slots.push(slot);
+                (table.slots.len() - 1) as u32
+            }
+        };
+        table.index.insert(address(&node), index);
+        Handle {
+            table: table.id(),
+            slot: index + 1,
+            generation,
+        }
+    }
+    /// Returns the node `handle` refers to, if it is still in the map.
+    pub(super) fn get(&self, handle: Handle) -> Option<OwnedNodeRef<K, V>> {
+        let table = self.0.as_ref().filter(|table| table.id() == handle.table)?;
+        let slot = table.slots.get((handle.slot as usize).wrapping_sub(1))?;
+        (slot.generation == handle.generation).then(|| slot.node.get_owned())
+    }
+    /// Moves the handle of `src`, if it has one, to `dst`, which its entry has
+    /// just moved into.
+    #[inline(always)]
+    pub(super) fn moved(&mut self, src: &OwnedNodeRef<K, V>, dst: &OwnedNodeRef<K, V>) {
+        if self.is_empty() {
+            return;
+        }
+        let table = self.0.as_mut().unwrap();
+        if let Some(index) = table.index.remove(&address(src)) {
+            table.index.insert(address(dst), index);
+            table.slots[index as usize].node = dst.get_node_ref();
+        }
+    }
+    /// Frees the slot of `node`, whose entry is leaving the map.
+    #[inline(always)]
+    pub(super) fn release(&mut self, node: OwnedNodeRef<K, V>) {
+        if self.is_empty() {
+            return;
+        }
+        let table = self.0.as_mut().unwrap();
+        if let Some(index) = table.index.remove(&address(&node)) {
+            table.slots[index as usize] = Slot {
+                node: NodeRef::none(),
+                generation: 0,
+            };
+            table.free.push(index);
+        }
+    }
+    /// Frees the slots of every node under `node`.
+    pub(super) fn release_subtree(&mut self, node: &NodeRef<K, V>) {
+        if let Some(owned) = node.clone().into_owned() {
+            self.release_subtree(&owned.next[0]);
+            self.release_subtree(&owned.next[1]);
+            self.release(owned);
+        }
+    }
+    /// Frees every slot, once the map holds no nodes. The table itself stays,
+    /// so the generations of handles issued before go on being rejected.
+    pub(super) fn clear(&mut self) {
+        if let Some(table) = self.0.as_mut() {
+            table.slots.clear();
+            table.free.clear();
+            table.index.clear();
+        }
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the entry `handle` refers to, or `None` if it has been removed.
+    ///
+    /// Takes `O(1)` time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Entry;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// let Entry::Vacant(entry) = map.entry(1) else { unreachable!() };
+    /// let handle = entry.insert_with_handle("a");
+    /// map.extend((2..100).map(|i| (i, "b")));
+    /// assert_eq!(map.get_by_handle(handle), Some((&1, &"a")));
+    /// map.remove(&1);
+    /// assert_eq!(map.get_by_handle(handle), None);
+    /// ```
+    pub fn get_by_handle(&self, handle: Handle) -> Option<(&K, &V)> {
+        self.handles
+            .get(handle)
+            .map(OwnedNodeRef::into_ref_key_value)
+    }
+    /// Returns the entry `handle` refers to with a mutable value, or `None` if
+    /// it has been removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Entry;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// let Entry::Vacant(entry) = map.entry("apples") else { unreachable!() };
+    /// let handle = entry.insert_with_handle(3);
+    /// if let Some((_, count)) = map.get_mut_by_handle(handle) {
+    ///     *count += 1;
+    /// }
+    /// assert_eq!(map[&"apples"], 4);
+    /// ```
+    pub fn get_mut_by_handle(&mut self, handle: Handle) -> Option<(&K, &mut V)> {
+        let node = self.handles.get(handle)?;
+        self.hook_get_mut(&node);
+        let (key, value) = &mut node.into_mut().key_value;
+        Some((&*key, value))
+    }
+    /// Removes the entry `handle` refers to without searching for its key,
+    /// returning it, or `None` if it has already been removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Entry;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([(1, 'a'), (3, 'c')]);
+    /// let Entry::Vacant(entry) = map.entry(2) else { unreachable!() };
+    /// let handle = entry.insert_with_handle('b');
+    /// assert_eq!(map.remove_by_handle(handle), Some((2, 'b')));
+    /// assert_eq!(map.remove_by_handle(handle), None);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn remove_by_handle(&mut self, handle: Handle) -> Option<(K, V)> {
+        let node = self.handles.get(handle)?;
+        let kv = self.raw_remove(node);
+        self.paranoid_shape();
+        Some(kv)
+    }
+}
@@ -1,11 +1,19 @@
-use super::node::OwnedNodeRef;
+use super::{
+    flag::LEFT,
+    node::{Node, NodeRef, OwnedNodeRef},
+    observer::Observer,
+};
+use crate::alloc::{Allocator, Global};
 use core::{
+    alloc::Layout,
     iter::{FusedIterator, Iterator},
     marker::PhantomData,
 };
 
 enum LazyPoint<K, V> {
+    /// The node is the next one to yield.
     Ready(OwnedNodeRef<K, V>),
+    /// The node was yielded last; the next one is its neighbour.
     Moving(OwnedNodeRef<K, V>),
     Empty,
 }
@@ -36,12 +44,9 @@ impl<'a, K, V> Clone for Iter<'a, K, V> {
 }
 
 impl<'a, K, V> Iter<'a, K, V> {
-    pub(super) fn new(root: OwnedNodeRef<K, V>, length: usize) -> Self {
+    pub(super) fn new(first: OwnedNodeRef<K, V>, last: OwnedNodeRef<K, V>, length: usize) -> Self {
         Self {
-            range: (
-                LazyPoint::Ready(root.clone()),
-                LazyPoint::Ready(root.clone()),
-            ),
+            range: (LazyPoint::Ready(first), LazyPoint::Ready(last)),
             length,
             _marker: PhantomData,
         }
@@ -63,7 +68,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
         }
         let new_begin = match self.range.0.clone() {
             LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.min() },
+            LazyPoint::Ready(first) => first,
             LazyPoint::Moving(begin) => unsafe { begin.next_unchecked() },
         };
         self.range.0 = LazyPoint::Moving(new_begin.clone());
@@ -82,7 +87,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
             {
                 match self.range.1 {
                     LazyPoint::Empty => return None,
-                    LazyPoint::Ready(root) => unsafe { root.max() },
+                    LazyPoint::Ready(last) => last,
                     LazyPoint::Moving(end) => unsafe { end.next_back_unchecked() },
                 }
             }
@@ -100,7 +105,7 @@ impl<'a, K, V> Iterator for Iter<'a, K, V> {
             {
                 match self.range.0 {
                     LazyPoint::Empty => return None,
-                    LazyPoint::Ready(root) => unsafe { root.min() },
+                    LazyPoint::Ready(first) => first,
                     LazyPoint::Moving(begin) => unsafe { begin.next_unchecked() },
                 }
             }
@@ -123,7 +128,7 @@ impl<'a, K: 'a, V: 'a> DoubleEndedIterator for Iter<'a, K, V> {
         }
         let new_end = match self.range.1.clone() {
             LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.max() },
+            LazyPoint::Ready(last) => last,
             LazyPoint::Moving(end) => unsafe { end.next_back_unchecked() },
         };
         self.range.1 = LazyPoint::Moving(new_end.clone());
@@ -140,17 +145,21 @@ impl<K, V> ExactSizeIterator for Iter<'_, K, V> {
 pub struct IterMut<'a, K: 'a, V: 'a> {
     range: (LazyPoint<K, V>, LazyPoint<K, V>),
     length: usize,
+    hooks: Option<&'a mut dyn Observer<K, V>>,
     _marker: PhantomData<&'a mut (K, V)>,
 }
 
 impl<'a, K, V> IterMut<'a, K, V> {
-    pub(super) fn new(root: OwnedNodeRef<K, V>, length: usize) -> Self {
+    pub(super) fn new(
+        first: OwnedNodeRef<K, V>,
+        last: OwnedNodeRef<K, V>,
+        length: usize,
+        hooks: Option<&'a mut dyn Observer<K, V>>,
+    ) -> Self {
         Self {
-            range: (
-                LazyPoint::Ready(root.clone()),
-                LazyPoint::Ready(root.clone()),
-            ),
+            range: (LazyPoint::Ready(first), LazyPoint::Ready(last)),
             length,
+            hooks,
             _marker: PhantomData,
         }
     }
@@ -158,9 +167,17 @@ impl<'a, K, V> IterMut<'a, K, V> {
         Self {
             range: (LazyPoint::Empty, LazyPoint::Empty),
             length: 0,
+            hooks: None,
             _marker: PhantomData,
         }
     }
+    fn yield_mut(&mut self, node: OwnedNodeRef<K, V>) -> (&'a K, &'a mut V) {
+        if let Some(hooks) = self.hooks.as_mut() {
+            hooks.on_get_mut(&node.key_value.0, &node.key_value.1);
+        }
+        let kv = &mut node.into_mut().key_value;
+        (&kv.0, &mut kv.1)
+    }
     pub fn iter(&self) -> Iter<'_, K, V> {
         Iter {
             range: (self.range.0.clone(), self.range.1.clone()),
@@ -178,45 +195,32 @@ impl<'a, K, V> Iterator for IterMut<'a, K, V> {
         }
         let new_begin = match self.range.0.clone() {
             LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.min() },
+            LazyPoint::Ready(first) => first,
             LazyPoint::Moving(begin) => unsafe { begin.next_unchecked() },
         };
         self.range.0 = LazyPoint::Moving(new_begin.clone());
         self.length -= 1;
-        let kv = &mut new_begin.into_mut().key_value;
-        Some((&kv.0, &mut kv.1))
+        Some(self.yield_mut(new_begin))
     }
     fn size_hint(&self) -> (usize, Option<usize>) {
         (self.length, Some(self.length))
     }
-    fn last(self) -> Option<(&'a K, &'a mut V)> {
+    fn last(mut self) -> Option<(&'a K, &'a mut V)> {
         if self.length == 0 {
             return None;
         }
-        let kv = &mut match self.range.1 {
+        let node = match self.range.1.clone() {
             LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.max() },
+            LazyPoint::Ready(last) => last,
             LazyPoint::Moving(end) => unsafe { end.next_back_unchecked() },
-        }
-        .into_mut()
-        .key_value;
-        Some((&kv.0, &mut kv.1))
+        };
+        Some(self.yield_mut(node))
     }
-    fn min(self) -> Option<(&'a K, &'a mut V)>
+    fn min(mut self) -> Option<(&'a K, &'a mut V)>
     where
         (&'a K, &'a mut V): Ord,
     {
-        if self.length == 0 {
-            return None;
-        }
-        let kv = &mut match self.range.0 {
-            LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.min() },
-            LazyPoint::Moving(begin) => unsafe { begin.next_unchecked() },
-        }
-        .into_mut()
-        .key_value;
-        Some((&kv.0, &mut kv.1))
+        self.next()
     }
     fn max(self) -> Option<(&'a K, &'a mut V)>
     where
@@ -232,13 +236,12 @@ impl<'a, K, V> DoubleEndedIterator for IterMut<'a, K, V> {
         }
         let new_end = match self.range.1.clone() {
             LazyPoint::Empty => return None,
-            LazyPoint::Ready(root) => unsafe { root.max() },
+            LazyPoint::Ready(last) => last,
             LazyPoint::Moving(end) => unsafe { end.next_back_unchecked() },
         };
         self.range.1 = LazyPoint::Moving(new_end.clone());
         self.length -= 1;
-        let kv = &mut new_end.into_mut().key_value;
-        Some((&kv.0, &mut kv.1))
+        Some(self.yield_mut(new_end))
     }
 }
 impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {
@@ -246,3 +249,148 @@ impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {
         self.length
     }
 }
+
+/// An iterator over a sub-range of the entries of an `RBTreeMap`, sorted by key.
+///
+/// This `struct` is created by [`RBTreeMap::range`](super::RBTreeMap::range).
+pub struct Range<'a, K: 'a, V: 'a> {
+    /// The next nodes to yield from the front and the back, or `None` once they have met.
+    ends: Option<[OwnedNodeRef<K, V>; 2]>,
+    _marker: PhantomData<&'a (K, V)>,
+}
+
+impl<K, V> Clone for Range<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            ends: self.ends.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V> Range<'_, K, V> {
+    /// Makes an iterator from `front` to `back` inclusive, or an empty one.
+    pub(super) fn new(ends: Option<[OwnedNodeRef<K, V>; 2]>) -> Self {
+        Self {
+            ends,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let [front, back] = self.ends.clone()?;
+        self.ends = match front.ptr == back.ptr {
+            true => None,
+            false => Some([unsafe { front.next_unchecked() }, back]),
+        };
+        Some(front.into_ref_key_value())
+    }
+    fn last(mut self) -> Option<Self::Item> {
+        self.next_back()
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let [front, back] = self.ends.clone()?;
+        self.ends = match front.ptr == back.ptr {
+            true => None,
+            false => Some([front, unsafe { back.next_back_unchecked() }]),
+        };
+        Some(back.into_ref_key_value())
+    }
+}
+
+impl<K, V> FusedIterator for Range<'_, K, V> {}
+
+pub struct IntoIter<K, V, A = Global>
+where
+    A: Allocator + Clone,
+{
+    front: NodeRef<K, V>,
+    length: usize,
+    alloc: A,
+}
+
+impl<K, V, A> IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(super) fn new(front: NodeRef<K, V>, length: usize, alloc: A) -> Self {
+        Self {
+            front,
+            length,
+            alloc,
+        }
+    }
+}
+
+impl<K, V, A> Iterator for IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+    fn next(&mut self) -> Option<(K, V)> {
+        if self.length == 0 {
+            return None;
+        }
+        //The front node has no left child, so its right subtree takes its place
+        //and the tree stays a valid search tree without any rebalancing.
+        let node = self.front.get_owned();
+        let kv = unsafe { core::ptr::read(&node.key_value) };
+        let right = node.next[1].clone().into_owned();
+        self.front = if node.flag.is_root() {
+            match right {
+                Some(mut right) => {
+                    right.flag.set_root();
+                    unsafe { right.min() }.get_node_ref()
+                }
+                None => NodeRef::none(),
+            }
+        } else {
+            let mut parent = node.parent.clone();
+            match right {
+                Some(right) => {
+                    parent.set_child(right.clone(), LEFT);
+                    unsafe { right.min() }.get_node_ref()
+                }
+                None => {
+                    parent.next[0] = NodeRef::none();
+                    parent.get_node_ref()
+                }
+            }
+        };
+        self.length -= 1;
+        unsafe {
+            self.alloc
+                .deallocate(node.unwrap().cast(), Layout::new::<Node<K, V>>());
+        }
+        Some(kv)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V, A> ExactSizeIterator for IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    fn len(&self) -> usize {
+        self.length
+    }
+}
+
+impl<K, V, A> FusedIterator for IntoIter<K, V, A> where A: Allocator + Clone {}
+
+impl<K, V, A> Drop for IntoIter<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        for _ in self.by_ref() {}
+    }
+}
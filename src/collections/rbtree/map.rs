@@ -1,9 +1,16 @@
+mod diff;
+mod merge;
+mod split;
 mod values;
 use super::{
+    comparable::Comparable,
     entry::{Entry, OccupiedEntry, VacantEntry},
     flag::Color,
-    iter::{Iter, IterMut},
+    handle::Handles,
+    iter::{IntoIter, Iter, IterMut, Range},
     node::{Node, NodeRef, SearchResult},
+    observer::Hooks,
+    stats::Counters,
 };
 use crate::{
     alloc::{Allocator, Global},
@@ -18,8 +25,10 @@ use core::{
     borrow::Borrow,
     cmp::Ordering,
     fmt::{Debug, Display},
-    ops::Index,
+    ops::{Bound, Index, RangeBounds},
 };
+pub use diff::{Change, Diff};
+pub use merge::{MergeJoin, MergeJoinItem};
 use values::{Values, ValuesMut};
 
 pub(super) enum NodeDesc<K, V> {
@@ -37,8 +46,16 @@ where
     A: Allocator + Clone,
 {
     pub(super) root: NodeRef<K, V>,
+    /// The leftmost and rightmost nodes, so both ends are reachable in `O(1)`.
+    pub(super) first: NodeRef<K, V>,
+    pub(super) last: NodeRef<K, V>,
+    /// The node last inserted, where sorted runs and hinted inserts continue.
+    pub(super) finger: NodeRef<K, V>,
     pub(super) alloc: A,
     pub(super) length: usize,
+    pub(super) hooks: Hooks<K, V, A>,
+    pub(super) handles: Handles<K, V, A>,
+    pub(super) counters: Counters,
 }
 impl<K, V, const N: usize> From<[(K, V); N]> for RBTreeMap<K, V>
 where
@@ -46,6 +63,8 @@ where
 {
     /// Converts a `[(K, V); N]` into a `BTreeMap<(K, V)>`.
     ///
+    /// Use [`RBTreeMap::from_in`] to allocate from another allocator.
+    ///
     /// ```
     /// use xsl::collections::RBTreeMap;
     ///
@@ -54,14 +73,8 @@ where
     /// print!("{:?}", map1);
     /// assert_eq!(map1, map2);
     /// ```
-    fn from(mut arr: [(K, V); N]) -> Self {
-        if N == 0 {
-            return RBTreeMap::new();
-        }
-
-        // use stable sort to preserve the insertion order.
-        arr.sort_by(|a, b| a.0.cmp(&b.0));
-        RBTreeMap::bulk_build_from_sorted_iter(arr.into_iter(), Global::default())
+    fn from(arr: [(K, V); N]) -> Self {
+        RBTreeMap::from_in(arr, Global::default())
     }
 }
 impl<K, V, A> Debug for RBTreeMap<K, V, A>
@@ -110,6 +123,32 @@ where
         self.iter()
     }
 }
+impl<K, V, A> IntoIterator for RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    /// Gets an owning iterator over the entries of the map, sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let map = RBTreeMap::from([(3, "c"), (1, "a"), (2, "b")]);
+    /// let v: Vec<_> = map.into_iter().collect();
+    /// assert_eq!(v, [(1, "a"), (2, "b"), (3, "c")]);
+    /// ```
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        let map = core::mem::ManuallyDrop::new(self);
+        let alloc = unsafe { core::ptr::read(&map.alloc) };
+        drop(unsafe { core::ptr::read(&map.hooks) });
+        drop(unsafe { core::ptr::read(&map.handles) });
+        IntoIter::new(map.first.clone(), map.length, alloc)
+    }
+}
 impl<K, V, A> PartialEq for RBTreeMap<K, V, A>
 where
     K: PartialEq,
@@ -141,14 +180,19 @@ where
 }
 impl<K: Ord, V, A: Allocator + Clone> Extend<(K, V)> for RBTreeMap<K, V, A> {
     #[inline]
+    /// Extends the map with the pairs of an iterator.
+    ///
+    /// Each pair is first tried right after the previously inserted one, so runs of
+    /// ascending keys are appended without searching from the root.
     fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
         iter.into_iter().for_each(move |(k, v)| {
-            self.insert(k, v);
+            self.insert_near_finger(k, v);
         });
     }
 }
-impl<K, V, Q> Index<&Q> for RBTreeMap<K, V>
+impl<K, V, A, Q> Index<&Q> for RBTreeMap<K, V, A>
 where
+    A: Allocator + Clone,
     K: Borrow<Q> + Ord,
     Q: ?Sized + Ord,
 {
@@ -178,63 +222,13 @@ where
     A: Allocator + Clone,
 {
     fn clone(&self) -> Self {
-        fn new_branch<K, V, A>(
-            alloc: &A,
-            src: OwnedNodeRef<K, V>,
-            mut dst: OwnedNodeRef<K, V>,
-            rela: u8,
-        ) -> (OwnedNodeRef<K, V>, OwnedNodeRef<K, V>)
-        where
-            K: Clone,
-            V: Clone,
-            A: Allocator,
-        {
-            let src_child_ptr = src.next[rela as usize].get_owned();
-            let src_child = src_child_ptr.clone();
-            let mut new_node = {
-                #[cfg(debug_assertions)]
-                {
-                    OwnedNodeRef::new_in(&alloc)
-                }
-                #[cfg(not(debug_assertions))]
-                {
-                    OwnedNodeRef::new_in(alloc)
-                }
-            };
-            new_node.init_from(&*src_child);
-            dst.next[rela as usize] = new_node.get_node_ref();
-            new_node.set_parent(dst, rela);
-            (src_child_ptr, new_node)
-        }
-        use crate::alloc::Vec;
-        let mut new_tree = Self::new_in(self.alloc.clone());
-        if self.is_empty() {
-            return new_tree;
-        }
-        let mut stack = Vec::new();
-        stack.push(new_branch(
-            &new_tree.alloc,
-            self.root.get_owned(),
-            new_tree.root.get_owned(),
-            ROOT,
-        ));
-        while let Some((src, dst)) = stack.pop() {
-            //First determine whether the child node is empty
-            //Simple performance test shows that the following code is faster than the next code
-            if !src.next[0].is_none() {
-                stack.push(new_branch(&new_tree.alloc, src.clone(), dst.clone(), LEFT));
-            }
-            if !src.next[1].is_none() {
-                stack.push(new_branch(&new_tree.alloc, src, dst, RIGHT));
-            }
-        }
-        new_tree.length = self.length;
-        new_tree
+        self.clone_in(self.alloc.clone())
     }
 }
 
-impl<K, V> Display for RBTreeMap<K, V>
+impl<K, V, A> Display for RBTreeMap<K, V, A>
 where
+    A: Allocator + Clone,
     K: Display,
     V: Display,
 {
@@ -267,7 +261,7 @@ where
     A: Allocator + Clone,
 {
     fn drop(&mut self) {
-        self.clear();
+        self.raw_clear();
     }
 }
 
@@ -288,26 +282,61 @@ where
     /// assert!(map.is_empty());
     /// ```
     pub fn clear(&mut self) {
+        if !self.hooks.is_empty() && !self.is_empty() {
+            for (k, v) in Iter::new(self.first.get_owned(), self.last.get_owned(), self.length) {
+                self.hooks.removed(k, v);
+            }
+        }
+        self.raw_clear();
+    }
+    fn raw_clear(&mut self) {
         if self.is_empty() {
             return;
         }
-        use crate::alloc::Vec;
-        let mut stack = Vec::new();
-        stack.push(self.root.clone());
-        while !stack.is_empty() {
-            let mut node = stack.pop().unwrap();
-            if node.is_none() {
-                continue;
-            }
-            stack.push(node.next[0].clone());
-            stack.push(node.next[1].clone());
+        // Free the nodes in pre-order. Right subtrees still to be freed are kept
+        // in a list linked through the parent pointers of their roots, with the
+        // last one marked by the root relation, so dropping needs no stack and
+        // cannot fail to allocate.
+        let mut node = self.root.get_owned();
+        let mut pending: Option<OwnedNodeRef<K, V>> = None;
+        loop {
+            let left = node.next[0].clone().into_owned();
+            let right = node.next[1].clone().into_owned();
+            let next = match (left, right) {
+                (Some(left), Some(mut right)) => {
+                    match pending.replace(right.clone()) {
+                        Some(below) => right.parent = below,
+                        None => {
+                            right.flag.set_rela(ROOT);
+                        }
+                    }
+                    Some(left)
+                }
+                (Some(child), None) | (None, Some(child)) => Some(child),
+                (None, None) => match pending.take() {
+                    Some(top) if !top.flag.is_root() => {
+                        pending = Some(top.parent.clone());
+                        Some(top)
+                    }
+                    top => top,
+                },
+            };
             unsafe {
-                core::ptr::drop_in_place(node.ptr.as_mut().unwrap().as_mut());
+                core::ptr::drop_in_place(node.ptr.as_mut());
                 self.alloc
-                    .deallocate(node.ptr.unwrap().cast(), Layout::new::<Node<K, V>>());
+                    .deallocate(node.ptr.cast(), Layout::new::<Node<K, V>>());
+            }
+            match next {
+                Some(next) => node = next,
+                None => break,
             }
         }
+        self.root = NodeRef::none();
+        self.first = NodeRef::none();
+        self.last = NodeRef::none();
+        self.finger = NodeRef::none();
         self.length = 0;
+        self.handles.clear();
     }
     /// Returns `true` if the map contains no elements.
     ///
@@ -484,7 +513,7 @@ where
         if self.is_empty() {
             Iter::new_empty()
         } else {
-            Iter::new(self.root.get_owned(), self.length)
+            Iter::new(self.first.get_owned(), self.last.get_owned(), self.length)
         }
     }
     /// Gets a mutable iterator over the entries of the map, sorted by key.
@@ -511,7 +540,12 @@ where
         if self.is_empty() {
             IterMut::new_empty()
         } else {
-            IterMut::new(self.root.get_owned(), self.length)
+            IterMut::new(
+                self.first.get_owned(),
+                self.last.get_owned(),
+                self.length,
+                self.hooks.as_observer(),
+            )
         }
     }
 }
@@ -577,6 +611,89 @@ where
             NodeDesc::NotFound(nd) => Entry::Vacant(VacantEntry::new(key, nd, self)),
         }
     }
+    /// Inserts a key-value pair, starting the search at the entry for `hint`.
+    ///
+    /// When `key` lies between `hint` and its neighbour, no search from the root is
+    /// needed. The hint is found in `O(1)` when it is the key inserted last; otherwise
+    /// it costs a lookup, and a hint that is not adjacent falls back to a full search.
+    /// Returns the old value like [`insert`](RBTreeMap::insert).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.insert(10, "a");
+    /// map.insert_hint(&10, 11, "b");
+    /// map.insert_hint(&11, 12, "c");
+    /// assert_eq!(map.insert_hint(&12, 11, "x"), Some("b"));
+    /// // A wrong hint still inserts correctly.
+    /// map.insert_hint(&12, 5, "d");
+    /// assert!(map.iter().map(|(k, _)| *k).eq([5, 10, 11, 12]));
+    /// ```
+    pub fn insert_hint(&mut self, hint: &K, key: K, value: V) -> Option<V> {
+        let near = match self.finger.clone().into_owned() {
+            Some(finger) if finger.key_value.0 == *hint => Some(finger),
+            _ => match self.raw_search(hint) {
+                NodeDesc::Found(node) => Some(node),
+                NodeDesc::NotFound(_) => None,
+            },
+        };
+        let nd = match near {
+            Some(near) => self.raw_search_near(near, &key),
+            None => self.raw_search(&key),
+        };
+        self.insert_at(nd, key, value)
+    }
+    /// Inserts next to the finger when `key` belongs there, like a sorted append.
+    fn insert_near_finger(&mut self, key: K, value: V) -> Option<V> {
+        let nd = match self.finger.clone().into_owned() {
+            Some(finger) => self.raw_search_near(finger, &key),
+            None => self.raw_search(&key),
+        };
+        self.insert_at(nd, key, value)
+    }
+    fn insert_at(&mut self, nd: NodeDesc<K, V>, key: K, value: V) -> Option<V> {
+        match nd {
+            NodeDesc::Found(node) => {
+                self.finger = node.get_node_ref();
+                Some(OccupiedEntry::new(node, self).insert(value))
+            }
+            NodeDesc::NotFound(nd) => {
+                VacantEntry::new(key, nd, self).insert_node(value);
+                None
+            }
+        }
+    }
+    /// Finds where `key` belongs if it is `near` or next to it, or searches from the root.
+    fn raw_search_near(&self, near: OwnedNodeRef<K, V>, key: &K) -> NodeDesc<K, V> {
+        let (rela, end) = match key.cmp(&near.key_value.0) {
+            Ordering::Equal => return NodeDesc::Found(near),
+            Ordering::Greater => (RIGHT, &self.last),
+            Ordering::Less => (LEFT, &self.first),
+        };
+        if end.ptr == Some(near.ptr) {
+            return NodeDesc::NotFound(NdNotFound::Normal(near, rela));
+        }
+        let neighbour = unsafe {
+            match rela {
+                RIGHT => near.next_unchecked(),
+                _ => near.next_back_unchecked(),
+            }
+        };
+        match (key.cmp(&neighbour.key_value.0), rela) {
+            (Ordering::Equal, _) => NodeDesc::Found(neighbour),
+            (Ordering::Less, RIGHT) | (Ordering::Greater, LEFT) => {
+                if near.next[rela as usize].is_none() {
+                    NodeDesc::NotFound(NdNotFound::Normal(near, rela))
+                } else {
+                    NodeDesc::NotFound(NdNotFound::Normal(neighbour, toggle_rela(rela)))
+                }
+            }
+            _ => self.raw_search(key),
+        }
+    }
 }
 impl<K, V, A> RBTreeMap<K, V, A>
 where
@@ -601,6 +718,14 @@ where
     where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
+    {
+        self.get_by(key)
+    }
+    /// Like [`get`](Self::get), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn get_by<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: ?Sized + Comparable<K>,
     {
         match self.raw_search(key) {
             NodeDesc::Found(node) => Some(&node.into_ref().key_value.1),
@@ -629,12 +754,97 @@ where
     where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
+    {
+        self.get_mut_by(key)
+    }
+    /// Like [`get_mut`](Self::get_mut), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn get_mut_by<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: ?Sized + Comparable<K>,
     {
         match self.raw_search(key) {
-            NodeDesc::Found(node) => Some(&mut node.into_mut().key_value.1),
+            NodeDesc::Found(node) => {
+                self.hook_get_mut(&node);
+                Some(&mut node.into_mut().key_value.1)
+            }
             NodeDesc::NotFound(_) => None,
         }
     }
+    /// Returns mutable references to the values corresponding to `N` keys at once.
+    ///
+    /// Returns `None` if any of the keys is missing, or if two of the keys refer
+    /// to the same entry.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form *must* match the ordering on the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([("a", 10), ("b", 0), ("c", 5)]);
+    /// if let Some([from, to]) = map.get_many_mut([&"a", &"b"]) {
+    ///     *from -= 3;
+    ///     *to += 3;
+    /// }
+    /// assert_eq!(map[&"a"], 7);
+    /// assert_eq!(map[&"b"], 3);
+    ///
+    /// assert!(map.get_many_mut([&"a", &"a"]).is_none());
+    /// assert!(map.get_many_mut([&"a", &"d"]).is_none());
+    /// ```
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let nodes = self.raw_search_many(keys)?;
+        for i in 1..N {
+            if nodes[..i].iter().any(|node| node.ptr == nodes[i].ptr) {
+                return None;
+            }
+        }
+        nodes.iter().for_each(|node| self.hook_get_mut(node));
+        Some(nodes.map(|node| &mut node.into_mut().key_value.1))
+    }
+    /// Returns mutable references to the values corresponding to `N` keys at once,
+    /// without checking that the keys refer to distinct entries.
+    ///
+    /// Returns `None` if any of the keys is missing.
+    ///
+    /// # Safety
+    ///
+    /// Calling this method with keys that refer to the same entry is *[undefined behavior]*
+    /// even if the resulting references are not used.
+    ///
+    /// [undefined behavior]: https://doc.rust-lang.org/reference/behavior-considered-undefined.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// if let Some([x, y]) = unsafe { map.get_many_unchecked_mut([&1, &2]) } {
+    ///     core::mem::swap(x, y);
+    /// }
+    /// assert_eq!(map[&1], "b");
+    /// assert_eq!(map[&2], "a");
+    /// ```
+    pub unsafe fn get_many_unchecked_mut<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let nodes = self.raw_search_many(keys)?;
+        nodes.iter().for_each(|node| self.hook_get_mut(node));
+        Some(nodes.map(|node| &mut node.into_mut().key_value.1))
+    }
 
     /// Returns `true` if the map contains a value for the specified key.
     ///
@@ -656,7 +866,15 @@ where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
     {
-        self.get(key).is_some()
+        self.contains_key_by(key)
+    }
+    /// Like [`contains_key`](Self::contains_key), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn contains_key_by<Q>(&self, key: &Q) -> bool
+    where
+        Q: ?Sized + Comparable<K>,
+    {
+        self.get_by(key).is_some()
     }
     /// Returns the key-value pair corresponding to the supplied key.
     ///
@@ -677,12 +895,67 @@ where
     where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
+    {
+        self.get_key_value_by(key)
+    }
+    /// Like [`get_key_value`](Self::get_key_value), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn get_key_value_by<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: ?Sized + Comparable<K>,
     {
         match self.raw_search(key) {
             NodeDesc::Found(node) => Some(node.into_ref_key_value()),
             NodeDesc::NotFound(_) => None,
         }
     }
+    /// Gets a double-ended iterator over the entries whose keys lie in `range`,
+    /// sorted by key.
+    ///
+    /// Finding both ends takes `O(log n)` time. A range whose start lies after
+    /// its end yields nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let map = RBTreeMap::from([(1, 'a'), (3, 'c'), (5, 'e'), (7, 'g')]);
+    /// assert!(map.range(2..=5).eq([(&3, &'c'), (&5, &'e')]));
+    /// assert!(map.range(4..).rev().eq([(&7, &'g'), (&5, &'e')]));
+    /// assert_eq!(map.range(6..2).next(), None);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        self.range_by(range)
+    }
+    /// Like [`range`](Self::range), but compares the bounds through [`Comparable`],
+    /// so they may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn range_by<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        Q: ?Sized + Comparable<K>,
+        R: RangeBounds<Q>,
+    {
+        let [_, front] = self.raw_partition_point(|key| match range.start_bound() {
+            Bound::Included(start) => start.compare(key).is_gt(),
+            Bound::Excluded(start) => start.compare(key).is_ge(),
+            Bound::Unbounded => false,
+        });
+        let before_end = |key: &K| match range.end_bound() {
+            Bound::Included(end) => end.compare(key).is_ge(),
+            Bound::Excluded(end) => end.compare(key).is_gt(),
+            Bound::Unbounded => true,
+        };
+        let [back, _] = self.raw_partition_point(before_end);
+        Range::new(match (front, back) {
+            (Some(front), Some(back)) if before_end(&front.key_value.0) => Some([front, back]),
+            _ => None,
+        })
+    }
 
     /// Removes a key from the map, returning the stored key and value if the key
     /// was previously in the map.
@@ -704,9 +977,21 @@ where
     where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
+    {
+        self.remove_entry_by(key)
+    }
+    /// Like [`remove_entry`](Self::remove_entry), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn remove_entry_by<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: ?Sized + Comparable<K>,
     {
         match self.raw_search(key) {
-            NodeDesc::Found(node) => Some(self.raw_remove(node)),
+            NodeDesc::Found(node) => {
+                let kv = self.raw_remove(node);
+                self.paranoid_shape();
+                Some(kv)
+            }
             NodeDesc::NotFound(_) => None,
         }
     }
@@ -731,7 +1016,15 @@ where
         K: Borrow<Q>,
         Q: ?Sized + Ord,
     {
-        self.remove_entry(key).map(|(_, v)| v)
+        self.remove_by(key)
+    }
+    /// Like [`remove`](Self::remove), but finds the key through [`Comparable`],
+    /// so it may be a type the key does not borrow as, such as [`Parts`](super::Parts).
+    pub fn remove_by<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        Q: ?Sized + Comparable<K>,
+    {
+        self.remove_entry_by(key).map(|(_, v)| v)
     }
     /// Gets an iterator over the values of the map, in order by key.
     ///
@@ -777,38 +1070,247 @@ impl<K, V, A> RBTreeMap<K, V, A>
 where
     A: Allocator + Clone,
 {
-    pub(super) fn new_in(alloc: A) -> Self {
+    /// Makes a new, empty `RBTreeMap` allocating its nodes from `alloc`.
+    ///
+    /// Does not allocate anything on its own.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use allocator_api2::alloc::Global;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new_in(Global);
+    /// map.insert(1, "a");
+    /// assert_eq!(map[&1], "a");
+    /// ```
+    pub fn new_in(alloc: A) -> Self {
         RBTreeMap {
             root: NodeRef::none(),
+            first: NodeRef::none(),
+            last: NodeRef::none(),
+            finger: NodeRef::none(),
             alloc,
             length: 0,
+            hooks: Hooks::new(),
+            handles: Handles::new(),
+            counters: Counters::new(),
+        }
+    }
+    /// Converts a `[(K, V); N]` into a map allocating from `alloc`.
+    ///
+    /// Later pairs overwrite the values of earlier pairs with equal keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use allocator_api2::alloc::Global;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let map = RBTreeMap::from_in([(3, "c"), (1, "a"), (3, "d")], Global);
+    /// assert!(map.into_iter().eq([(1, "a"), (3, "d")]));
+    /// ```
+    pub fn from_in<const N: usize>(mut arr: [(K, V); N], alloc: A) -> Self
+    where
+        K: Ord,
+    {
+        if N == 0 {
+            return RBTreeMap::new_in(alloc);
+        }
+
+        // use stable sort to preserve the insertion order.
+        arr.sort_by(|a, b| a.0.cmp(&b.0));
+        RBTreeMap::bulk_build_from_sorted_iter(arr, alloc)
+    }
+    /// Returns a reference to the map's allocator.
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+    /// Makes a copy of the map whose nodes are allocated from `alloc`.
+    ///
+    /// The copy has the same shape as the original, so no keys are compared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use allocator_api2::alloc::Global;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let map = RBTreeMap::from([(1, "a"), (2, "b"), (3, "c")]);
+    /// let copy = map.clone_in(Global);
+    /// assert!(map.iter().eq(copy.iter()));
+    /// assert_eq!(map, copy.clone_in(map.allocator().clone()));
+    /// ```
+    pub fn clone_in<B>(&self, alloc: B) -> RBTreeMap<K, V, B>
+    where
+        K: Clone,
+        V: Clone,
+        B: Allocator + Clone,
+    {
+        fn new_node<K, V, B>(alloc: &B, src: &OwnedNodeRef<K, V>) -> OwnedNodeRef<K, V>
+        where
+            K: Clone,
+            V: Clone,
+            B: Allocator,
+        {
+            let mut new_node = {
+                #[cfg(debug_assertions)]
+                {
+                    OwnedNodeRef::new_in(&alloc)
+                }
+                #[cfg(not(debug_assertions))]
+                {
+                    OwnedNodeRef::new_in(alloc)
+                }
+            };
+            unsafe {
+                core::ptr::write(&mut new_node.key_value, src.key_value.clone());
+            }
+            new_node.flag = src.flag;
+            new_node
         }
+        let mut new_tree = RBTreeMap::new_in(alloc);
+        let Some(mut src) = self.root.clone().into_owned() else {
+            return new_tree;
+        };
+        let mut dst = new_node(&new_tree.alloc, &src);
+        new_tree.root = dst.get_node_ref();
+        // Walk both trees in pre-order, climbing through parent pointers to the
+        // nearest ancestor whose right subtree is still to be copied.
+        'copy: loop {
+            let rela = if src.next[LEFT as usize].is_some() {
+                LEFT
+            } else if src.next[RIGHT as usize].is_some() {
+                RIGHT
+            } else {
+                loop {
+                    if src.flag.is_root() {
+                        break 'copy;
+                    }
+                    let from_left = src.flag.is_left();
+                    src = src.parent.clone();
+                    dst = dst.parent.clone();
+                    if from_left && src.next[RIGHT as usize].is_some() {
+                        break RIGHT;
+                    }
+                }
+            };
+            src = src.next[rela as usize].get_owned();
+            let child = new_node(&new_tree.alloc, &src);
+            dst.set_child(child.clone(), rela);
+            dst = child;
+        }
+        new_tree.first = unsafe { new_tree.root.get_owned().min() }.get_node_ref();
+        new_tree.last = unsafe { new_tree.root.get_owned().max() }.get_node_ref();
+        new_tree.length = self.length;
+        new_tree
+    }
+}
+impl<K, V> Default for RBTreeMap<K, V> {
+    /// Creates an empty `RBTreeMap`.
+    fn default() -> RBTreeMap<K, V> {
+        RBTreeMap::new()
     }
 }
 impl<K, V> RBTreeMap<K, V> {
     pub fn new() -> Self {
-        let alloc = Global::default();
-        RBTreeMap {
-            root: NodeRef::none(),
-            alloc,
-            length: 0,
-        }
+        RBTreeMap::new_in(Global::default())
     }
 }
 impl<K, V, A> RBTreeMap<K, V, A>
 where
     A: Allocator + Clone,
 {
+    /// Builds a map from an iterator whose keys are in ascending order, in linear time.
+    /// It allocates nothing but the nodes, and those from `alloc`.
+    ///
+    /// Adjacent equal keys are merged the same way repeated [`insert`] calls would merge
+    /// them: the first key is kept and the last value wins.
+    ///
+    /// [`insert`]: RBTreeMap::insert
     pub fn bulk_build_from_sorted_iter<I>(iter: I, alloc: A) -> Self
     where
         I: IntoIterator<Item = (K, V)>,
         K: Ord,
         A: Allocator + Clone,
     {
+        /// Links the first `len` nodes of `chain` into a balanced subtree.
+        fn build<K, V>(
+            chain: &mut Option<OwnedNodeRef<K, V>>,
+            len: usize,
+            depth: u32,
+            red_depth: u32,
+        ) -> Option<OwnedNodeRef<K, V>> {
+            if len == 0 {
+                return None;
+            }
+            let left_len = (len - 1) / 2;
+            let left = build(chain, left_len, depth + 1, red_depth);
+            let mut node = chain.take().unwrap();
+            *chain = node.next[RIGHT as usize].clone().into_owned();
+            node.next = [NodeRef::none(), NodeRef::none()];
+            if let Some(left) = left {
+                node.set_child(left, LEFT);
+            }
+            if let Some(right) = build(chain, len - 1 - left_len, depth + 1, red_depth) {
+                node.set_child(right, RIGHT);
+            }
+            //Only the deepest level can be incomplete, so making it red balances every path
+            if depth == red_depth && depth != 0 {
+                node.flag.set_red();
+            } else {
+                node.flag.set_black();
+            }
+            Some(node)
+        }
+        // The entries first hang off each other as right children. That is a
+        // tree, if not a balanced one, so the map frees them should the iterator
+        // or `Eq` panic, and they take no memory but their own nodes.
         let mut tree = Self::new_in(alloc);
+        let mut tail: Option<OwnedNodeRef<K, V>> = None;
         for (k, v) in iter {
-            tree.insert(k, v);
+            if let Some(tail) = &mut tail {
+                if tail.key_value.0 == k {
+                    tail.key_value.1 = v;
+                    continue;
+                }
+                debug_assert!(tail.key_value.0 < k);
+            }
+            let mut node = OwnedNodeRef::new_in(
+                #[cfg(debug_assertions)]
+                {
+                    &tree.alloc
+                },
+                #[cfg(not(debug_assertions))]
+                {
+                    tree.alloc.clone()
+                },
+            );
+            unsafe {
+                core::ptr::write(&mut node.key_value, (k, v));
+            }
+            match &mut tail {
+                Some(tail) => tail.set_child(node.clone(), RIGHT),
+                None => {
+                    node.flag.set_root();
+                    tree.root = node.get_node_ref();
+                }
+            }
+            tree.length += 1;
+            tail = Some(node);
         }
+        let len = tree.length;
+        if len == 0 {
+            return tree;
+        }
+        let red_depth = usize::BITS - 1 - len.leading_zeros();
+        let mut chain = tree.root.clone().into_owned();
+        let mut root = build(&mut chain, len, 0, red_depth).unwrap();
+        root.flag.set_root();
+        tree.root = root.get_node_ref();
+        tree.first = unsafe { root.min() }.get_node_ref();
+        tree.last = unsafe { root.max() }.get_node_ref();
+        tree.paranoid();
         tree
     }
 }
@@ -817,28 +1319,66 @@ impl<K, V, A> RBTreeMap<K, V, A>
 where
     A: Allocator + Clone,
 {
+    #[inline(always)]
+    pub(super) fn hook_get_mut(&mut self, node: &OwnedNodeRef<K, V>) {
+        self.hooks.get_mut(&node.key_value.0, &node.key_value.1);
+    }
     pub(super) fn raw_remove(&mut self, node: OwnedNodeRef<K, V>) -> (K, V) {
+        self.hooks.removed(&node.key_value.0, &node.key_value.1);
+        self.handles.release(node.clone());
+        self.finger = NodeRef::none();
         let kv = unsafe { core::mem::transmute_copy(&node.key_value) };
-        fn replace<K, V>(mut node: OwnedNodeRef<K, V>) -> OwnedNodeRef<K, V> {
+        /// Moves the entry of `src` into `dst`; a cached end and a handle follow
+        /// their entry.
+        fn move_key_value<K, V, A: Allocator + Clone>(
+            src: &OwnedNodeRef<K, V>,
+            dst: &mut OwnedNodeRef<K, V>,
+            ends: &mut [NodeRef<K, V>; 2],
+            handles: &mut Handles<K, V, A>,
+        ) {
+            unsafe {
+                core::ptr::copy_nonoverlapping(&src.key_value, &mut dst.key_value, 1);
+            }
+            handles.moved(src, dst);
+            for end in ends {
+                if end.ptr == Some(src.ptr) {
+                    *end = dst.get_node_ref();
+                }
+            }
+        }
+        fn replace<K, V, A: Allocator + Clone>(
+            mut node: OwnedNodeRef<K, V>,
+            ends: &mut [NodeRef<K, V>; 2],
+            handles: &mut Handles<K, V, A>,
+        ) -> OwnedNodeRef<K, V> {
             if node.next[1].is_none() {
                 if node.next[0].is_none() {
                     return node;
                 }
-                unsafe {
-                    core::ptr::copy_nonoverlapping(&node.next[0].key_value, &mut node.key_value, 1);
-                }
-                return node.next[0].get_owned();
+                let child = node.next[0].get_owned();
+                move_key_value(&child, &mut node, ends, handles);
+                return child;
             }
             let repl_node = unsafe { node.next[1].get_owned().min() };
-            unsafe {
-                core::ptr::copy_nonoverlapping(&repl_node.key_value, &mut node.key_value, 1);
-            }
-            return replace(repl_node);
+            move_key_value(&repl_node, &mut node, ends, handles);
+            replace(repl_node, ends, handles)
         }
-        let repl_node = replace(node);
+        let mut ends = [self.first.clone(), self.last.clone()];
+        let repl_node = replace(node, &mut ends, &mut self.handles);
         let mut parent = repl_node.parent.clone();
         let rela = repl_node.flag.rela();
         let color = repl_node.flag.color();
+        // A freed end still holding its own entry is a leaf, so its parent is
+        // the next entry in order.
+        for end in &mut ends {
+            if end.ptr == Some(repl_node.ptr) {
+                *end = match rela {
+                    ROOT => NodeRef::none(),
+                    _ => parent.get_node_ref(),
+                };
+            }
+        }
+        [self.first, self.last] = ends;
         self.length -= 1;
         unsafe {
             self.alloc
@@ -857,6 +1397,7 @@ where
         let mut brother = parent.next[toggle_rela as usize].get_owned();
         let prela = parent.flag.rela();
         if brother.flag.is_red() {
+            self.counters.rotate(2);
             if parent.flag.is_root() {
                 brother.flag.set_root();
                 self.root = brother.get_node_ref();
@@ -896,10 +1437,11 @@ where
                 brother.next[toggle_rela as usize].clone().into_owned(),
             ) {
                 (None, None) => {
+                    self.counters.recolor();
                     brother.flag.set_red();
                     if !parent.flag.is_root() {
                         if parent.flag.is_black() {
-                            if let Some(new_root) = parent.parent.rasie(prela) {
+                            if let Some(new_root) = parent.parent.rasie(prela, &mut self.counters) {
                                 self.root = new_root.get_node_ref();
                             }
                         } else {
@@ -908,6 +1450,7 @@ where
                     }
                 }
                 (Some(mut lnephew), None) => {
+                    self.counters.rotate(2);
                     if parent.flag.is_root() {
                         lnephew.flag.set_root();
                         self.root = lnephew.get_node_ref();
@@ -925,6 +1468,7 @@ where
                     lnephew.set_child(parent, rela);
                 }
                 (lnephew, Some(mut rnephew)) => {
+                    self.counters.rotate(1);
                     if parent.flag.is_root() {
                         brother.flag.set_root();
                         self.root = brother.get_node_ref();
@@ -952,10 +1496,9 @@ where
     }
     pub(super) fn raw_search<Q>(&self, key: &Q) -> NodeDesc<K, V>
     where
-        K: Borrow<Q>,
-        Q: ?Sized + Ord,
+        Q: ?Sized + Comparable<K>,
     {
-        if self.len() == 0 {
+        if self.is_empty() {
             return NodeDesc::NotFound(NdNotFound::Root);
         }
         match self.root.get_owned().search(key) {
@@ -965,17 +1508,35 @@ where
             }
         }
     }
+    /// Finds the nodes of `keys`, which may repeat. It borrows the map mutably
+    /// as the callers lend out the values of the nodes it returns.
+    fn raw_search_many<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[OwnedNodeRef<K, V>; N]>
+    where
+        Q: ?Sized + Comparable<K>,
+    {
+        let mut nodes: [Option<OwnedNodeRef<K, V>>; N] = core::array::from_fn(|_| None);
+        for (node, key) in nodes.iter_mut().zip(keys) {
+            match self.raw_search(key) {
+                NodeDesc::Found(found) => *node = Some(found),
+                NodeDesc::NotFound(_) => return None,
+            }
+        }
+        Some(nodes.map(Option::unwrap))
+    }
     pub fn raw_first(&self) -> Option<OwnedNodeRef<K, V>> {
         if self.is_empty() {
             return None;
         }
-        Some(unsafe { self.root.get_owned().min() })
+        Some(self.first.get_owned())
     }
     pub fn raw_last(&self) -> Option<OwnedNodeRef<K, V>> {
         if self.is_empty() {
             return None;
         }
-        Some(unsafe { self.root.get_owned().max() })
+        Some(self.last.get_owned())
     }
 }
 mod tests;
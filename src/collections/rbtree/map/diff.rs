@@ -0,0 +1,152 @@
+use super::{MergeJoin, MergeJoinItem, RBTreeMap};
+use crate::{alloc::Allocator, collections::rbtree::iter::Iter};
+use core::iter::FusedIterator;
+
+/// A difference between two versions of a map, produced by [`RBTreeMap::diff`]
+/// and applied by [`RBTreeMap::apply_patch`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change<K, V> {
+    /// The key is only in the new map.
+    Added(K, V),
+    /// The key is only in the old map.
+    Removed(K, V),
+    /// The key is in both maps with different values, old then new.
+    Changed(K, V, V),
+}
+
+impl<K, V> Change<K, V> {
+    /// Returns the key the change is about.
+    pub fn key(&self) -> &K {
+        match self {
+            Change::Added(k, _) | Change::Removed(k, _) | Change::Changed(k, _, _) => k,
+        }
+    }
+}
+
+impl<K, V> Change<&K, &V>
+where
+    K: Clone,
+    V: Clone,
+{
+    /// Clones the key and values, so the change can outlive the maps it came from.
+    pub fn cloned(self) -> Change<K, V> {
+        match self {
+            Change::Added(k, v) => Change::Added(k.clone(), v.clone()),
+            Change::Removed(k, v) => Change::Removed(k.clone(), v.clone()),
+            Change::Changed(k, old, new) => Change::Changed(k.clone(), old.clone(), new.clone()),
+        }
+    }
+}
+
+/// A lazy iterator over the [`Change`]s between two maps, in key order.
+///
+/// This `struct` is created by [`RBTreeMap::diff`].
+pub struct Diff<'a, K, V> {
+    join: MergeJoin<Iter<'a, K, V>, Iter<'a, K, V>>,
+}
+
+impl<'a, K, V> Iterator for Diff<'a, K, V>
+where
+    K: Ord,
+    V: PartialEq,
+{
+    type Item = Change<&'a K, &'a V>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.join.next()? {
+                MergeJoinItem::Left(k, v) => return Some(Change::Removed(k, v)),
+                MergeJoinItem::Right(k, v) => return Some(Change::Added(k, v)),
+                MergeJoinItem::Both(k, old, new) if old != new => {
+                    return Some(Change::Changed(k, old, new))
+                }
+                MergeJoinItem::Both(..) => {}
+            }
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.join.size_hint().1)
+    }
+}
+
+impl<K, V> FusedIterator for Diff<'_, K, V>
+where
+    K: Ord,
+    V: PartialEq,
+{
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Gets a lazy iterator over the changes that turn `old` into `new`, in key order.
+    ///
+    /// The two maps are walked side by side in `O(n + m)` time. Maps never share
+    /// nodes, so there are no common subtrees to skip, but diffing a map against
+    /// itself yields nothing without a walk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Change;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let old = RBTreeMap::from([(1, "a"), (2, "b"), (3, "c")]);
+    /// let new = RBTreeMap::from([(2, "b"), (3, "C"), (4, "d")]);
+    /// let changes: Vec<_> = RBTreeMap::diff(&old, &new).collect();
+    /// assert_eq!(
+    ///     changes,
+    ///     [
+    ///         Change::Removed(&1, &"a"),
+    ///         Change::Changed(&3, &"c", &"C"),
+    ///         Change::Added(&4, &"d"),
+    ///     ]
+    /// );
+    /// ```
+    pub fn diff<'a>(old: &'a Self, new: &'a Self) -> Diff<'a, K, V>
+    where
+        V: PartialEq,
+    {
+        let join = match core::ptr::eq(old, new) {
+            true => MergeJoin::new(Iter::new_empty(), Iter::new_empty()),
+            false => MergeJoin::new(old.iter(), new.iter()),
+        };
+        Diff { join }
+    }
+    /// Applies the changes from [`diff`](Self::diff), turning the old map into the new one.
+    ///
+    /// Each change takes `O(log n)` time, and runs of added keys in order take
+    /// `O(1)` amortized like [`extend`](Extend::extend). A `Removed` change for a
+    /// missing key is ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let old = RBTreeMap::from([(1, 'a'), (2, 'b')]);
+    /// let new = RBTreeMap::from([(2, 'B'), (3, 'c')]);
+    /// let patch: Vec<_> = RBTreeMap::diff(&old, &new).map(|c| c.cloned()).collect();
+    ///
+    /// let mut synced = old.clone();
+    /// synced.apply_patch(patch);
+    /// assert_eq!(synced, new);
+    /// ```
+    pub fn apply_patch<I>(&mut self, patch: I)
+    where
+        I: IntoIterator<Item = Change<K, V>>,
+    {
+        for change in patch {
+            match change {
+                Change::Added(k, v) | Change::Changed(k, _, v) => {
+                    self.insert_near_finger(k, v);
+                }
+                Change::Removed(k, _) => {
+                    self.remove(&k);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::RBTreeMap;
+use crate::{alloc::Allocator, collections::rbtree::iter::Iter};
+use core::{
+    cmp::Ordering,
+    iter::{FusedIterator, Peekable},
+};
+
+/// An entry produced by [`MergeJoin`], telling which of the two maps hold the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeJoinItem<K, L, R> {
+    /// The key is only in the left map.
+    Left(K, L),
+    /// The key is only in the right map.
+    Right(K, R),
+    /// The key is in both maps.
+    Both(K, L, R),
+}
+
+/// A lazy iterator walking two sorted sequences of key-value pairs side by side.
+///
+/// This `struct` is created by [`RBTreeMap::merge_join`].
+pub struct MergeJoin<I, J>
+where
+    I: Iterator,
+    J: Iterator,
+{
+    left: Peekable<I>,
+    right: Peekable<J>,
+}
+
+impl<I, J> MergeJoin<I, J>
+where
+    I: Iterator,
+    J: Iterator,
+{
+    pub(super) fn new(left: I, right: J) -> Self {
+        Self {
+            left: left.peekable(),
+            right: right.peekable(),
+        }
+    }
+}
+
+impl<K, L, R, I, J> Iterator for MergeJoin<I, J>
+where
+    K: Ord,
+    I: Iterator<Item = (K, L)>,
+    J: Iterator<Item = (K, R)>,
+{
+    type Item = MergeJoinItem<K, L, R>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ord = match (self.left.peek(), self.right.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((l, _)), Some((r, _))) => l.cmp(r),
+        };
+        Some(match ord {
+            Ordering::Less => {
+                let (k, l) = self.left.next().unwrap();
+                MergeJoinItem::Left(k, l)
+            }
+            Ordering::Greater => {
+                let (k, r) = self.right.next().unwrap();
+                MergeJoinItem::Right(k, r)
+            }
+            Ordering::Equal => {
+                let (k, l) = self.left.next().unwrap();
+                let (_, r) = self.right.next().unwrap();
+                MergeJoinItem::Both(k, l, r)
+            }
+        })
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (left_lower, left_upper) = self.left.size_hint();
+        let (right_lower, right_upper) = self.right.size_hint();
+        let upper = match (left_upper, right_upper) {
+            (Some(l), Some(r)) => l.checked_add(r),
+            _ => None,
+        };
+        (left_lower.max(right_lower), upper)
+    }
+}
+
+impl<K, L, R, I, J> FusedIterator for MergeJoin<I, J>
+where
+    K: Ord,
+    I: FusedIterator<Item = (K, L)>,
+    J: FusedIterator<Item = (K, R)>,
+{
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Gets a lazy iterator walking this map and `other` together in key order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::MergeJoinItem;
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let a = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// let b = RBTreeMap::from([(2, 20), (3, 30)]);
+    /// let joined: Vec<_> = a.merge_join(&b).collect();
+    /// assert_eq!(
+    ///     joined,
+    ///     [
+    ///         MergeJoinItem::Left(&1, &"a"),
+    ///         MergeJoinItem::Both(&2, &"b", &20),
+    ///         MergeJoinItem::Right(&3, &30),
+    ///     ]
+    /// );
+    /// ```
+    pub fn merge_join<'a, V2, A2>(
+        &'a self,
+        other: &'a RBTreeMap<K, V2, A2>,
+    ) -> MergeJoin<Iter<'a, K, V>, Iter<'a, K, V2>>
+    where
+        A2: Allocator + Clone,
+    {
+        MergeJoin::new(self.iter(), other.iter())
+    }
+    /// Consumes both maps and returns every entry of either one, in linear time.
+    ///
+    /// Values of keys present in both maps are merged with `combine`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let a = RBTreeMap::from([(1, 1), (2, 2)]);
+    /// let b = RBTreeMap::from([(2, 20), (3, 30)]);
+    /// let union = a.union_with(b, |_, x, y| x + y);
+    /// assert_eq!(union, RBTreeMap::from([(1, 1), (2, 22), (3, 30)]));
+    /// ```
+    pub fn union_with<A2, F>(self, other: RBTreeMap<K, V, A2>, mut combine: F) -> Self
+    where
+        A2: Allocator + Clone,
+        F: FnMut(&K, V, V) -> V,
+    {
+        let alloc = self.alloc.clone();
+        let merged = MergeJoin::new(self.into_iter(), other.into_iter()).map(|item| match item {
+            MergeJoinItem::Left(k, v) | MergeJoinItem::Right(k, v) => (k, v),
+            MergeJoinItem::Both(k, l, r) => {
+                let v = combine(&k, l, r);
+                (k, v)
+            }
+        });
+        RBTreeMap::bulk_build_from_sorted_iter(merged, alloc)
+    }
+    /// Consumes both maps and returns the keys present in both, in linear time.
+    ///
+    /// The values of each common key are merged with `combine`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let a = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// let b = RBTreeMap::from([(2, 20), (3, 30)]);
+    /// let both = a.intersection_with(b, |_, x, y| (x, y));
+    /// assert_eq!(both, RBTreeMap::from([(2, ("b", 20))]));
+    /// ```
+    pub fn intersection_with<V2, W, A2, F>(
+        self,
+        other: RBTreeMap<K, V2, A2>,
+        mut combine: F,
+    ) -> RBTreeMap<K, W, A>
+    where
+        A2: Allocator + Clone,
+        F: FnMut(&K, V, V2) -> W,
+    {
+        let alloc = self.alloc.clone();
+        let merged =
+            MergeJoin::new(self.into_iter(), other.into_iter()).filter_map(|item| match item {
+                MergeJoinItem::Both(k, l, r) => {
+                    let w = combine(&k, l, r);
+                    Some((k, w))
+                }
+                _ => None,
+            });
+        RBTreeMap::bulk_build_from_sorted_iter(merged, alloc)
+    }
+    /// Consumes both maps and returns the entries whose keys are not in `other`,
+    /// in linear time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let a = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// let b = RBTreeMap::from([(2, ()), (3, ())]);
+    /// assert_eq!(a.difference(b), RBTreeMap::from([(1, "a")]));
+    /// ```
+    pub fn difference<V2, A2>(self, other: RBTreeMap<K, V2, A2>) -> Self
+    where
+        A2: Allocator + Clone,
+    {
+        let alloc = self.alloc.clone();
+        let merged =
+            MergeJoin::new(self.into_iter(), other.into_iter()).filter_map(|item| match item {
+                MergeJoinItem::Left(k, v) => Some((k, v)),
+                _ => None,
+            });
+        RBTreeMap::bulk_build_from_sorted_iter(merged, alloc)
+    }
+}
@@ -0,0 +1,411 @@
+use super::RBTreeMap;
+use crate::{
+    alloc::Allocator,
+    collections::rbtree::{
+        comparable::Comparable,
+        flag::{toggle_rela, LEFT, RIGHT},
+        node::{NodeRef, OwnedNodeRef},
+        stats::Counters,
+    },
+};
+use core::{
+    borrow::Borrow,
+    ops::{Bound, RangeBounds},
+};
+
+/// A detached tree: a black root, or none, and the black height below it.
+struct Tree<K, V> {
+    root: NodeRef<K, V>,
+    black_height: usize,
+}
+
+impl<K, V> Tree<K, V> {
+    fn empty() -> Self {
+        Tree {
+            root: NodeRef::none(),
+            black_height: 0,
+        }
+    }
+    /// Cuts the child on side `rela` off `node`, the root of a tree with `black_height`.
+    fn child(node: &OwnedNodeRef<K, V>, rela: u8, black_height: usize) -> Self {
+        let root = node.next[rela as usize].clone();
+        let mut black_height = black_height - 1;
+        if let Some(mut child) = root.clone().into_owned() {
+            // a red child turns black as a root, adding a level
+            black_height += child.flag.is_red() as usize;
+            child.flag.set_root();
+        }
+        Tree { root, black_height }
+    }
+}
+
+/// Returns `true` if neither child of `node` is red.
+fn children_black<K, V>(node: &OwnedNodeRef<K, V>) -> bool {
+    node.next
+        .iter()
+        .all(|child| child.is_none() || child.flag.is_black())
+}
+
+/// Joins `left`, `mid` and `right` into one tree in `O(1 + |hl - hr|)` time.
+///
+/// Every key in `left` must be less than `mid`'s, and every key in `right` greater.
+fn join<K, V>(
+    left: Tree<K, V>,
+    mut mid: OwnedNodeRef<K, V>,
+    right: Tree<K, V>,
+    counters: &mut Counters,
+) -> Tree<K, V> {
+    mid.next = [NodeRef::none(), NodeRef::none()];
+    if left.black_height == right.black_height {
+        mid.flag.set_root();
+        if let Some(left) = left.root.into_owned() {
+            mid.set_child(left, LEFT);
+        }
+        if let Some(right) = right.root.into_owned() {
+            mid.set_child(right, RIGHT);
+        }
+        return Tree {
+            root: mid.get_node_ref(),
+            black_height: left.black_height + 1,
+        };
+    }
+    // hang `mid` off the inner spine of the taller tree, where the black heights meet
+    let (tall, short, side) = if left.black_height > right.black_height {
+        (left, right, RIGHT)
+    } else {
+        (right, left, LEFT)
+    };
+    let root = tall.root.get_owned();
+    let mut parent = root.clone();
+    let mut black_height = tall.black_height;
+    let child = loop {
+        let child = parent.next[side as usize].clone();
+        let child_height = black_height - parent.flag.is_black() as usize;
+        if child_height == short.black_height && (child.is_none() || child.flag.is_black()) {
+            break child;
+        }
+        parent = child.get_owned();
+        black_height = child_height;
+    };
+    mid.flag.set_red();
+    if let Some(child) = child.into_owned() {
+        mid.set_child(child, toggle_rela(side));
+    }
+    if let Some(short) = short.root.into_owned() {
+        mid.set_child(short, side);
+    }
+    parent.set_child(mid.clone(), side);
+    let mut tree = tall;
+    if parent.flag.is_red() {
+        let had_red_child = !children_black(&root);
+        let new_root = if mid.next.iter().all(NodeRef::is_none) {
+            mid.double_red_adjust(counters)
+        } else {
+            mid.rest_double_red_adjust(counters)
+        };
+        match new_root {
+            Some(new_root) => tree.root = new_root.get_node_ref(),
+            // only recolouring the root's red children blackens every path
+            None => tree.black_height += (had_red_child && children_black(&root)) as usize,
+        }
+    }
+    tree
+}
+
+/// Splits `tree` into the keys failing `goes_right` and the keys passing it,
+/// in `O(log n)` time. The keys passing must come after the keys failing.
+fn split<K, V, F>(tree: Tree<K, V>, goes_right: &mut F, counters: &mut Counters) -> [Tree<K, V>; 2]
+where
+    F: FnMut(&K) -> bool,
+{
+    let Some(node) = tree.root.into_owned() else {
+        return [Tree::empty(), Tree::empty()];
+    };
+    let left = Tree::child(&node, LEFT, tree.black_height);
+    let right = Tree::child(&node, RIGHT, tree.black_height);
+    if goes_right(&node.key_value.0) {
+        let [left, mid] = split(left, goes_right, counters);
+        [left, join(mid, node, right, counters)]
+    } else {
+        let [mid, right] = split(right, goes_right, counters);
+        [join(left, node, mid, counters), right]
+    }
+}
+
+/// Cuts the smallest node out of a non-empty `tree`.
+fn split_first<K, V>(
+    tree: Tree<K, V>,
+    counters: &mut Counters,
+) -> (OwnedNodeRef<K, V>, Tree<K, V>) {
+    let node = tree.root.get_owned();
+    let left = Tree::child(&node, LEFT, tree.black_height);
+    let right = Tree::child(&node, RIGHT, tree.black_height);
+    if left.root.is_none() {
+        return (node, right);
+    }
+    let (first, left) = split_first(left, counters);
+    (first, join(left, node, right, counters))
+}
+
+/// Joins two trees whose keys are all less in `left` than in `right`.
+fn join_pair<K, V>(left: Tree<K, V>, right: Tree<K, V>, counters: &mut Counters) -> Tree<K, V> {
+    if right.root.is_none() {
+        return left;
+    }
+    let (mid, right) = split_first(right, counters);
+    join(left, mid, right, counters)
+}
+
+/// Returns the lengths of two trees holding `total` nodes between them.
+///
+/// Both are walked in order at once and the walk stops when either runs out, so
+/// it takes `O(min(a, b))` steps for trees of `a` and `b` nodes.
+fn lengths<K, V>(trees: [&NodeRef<K, V>; 2], total: usize) -> [usize; 2] {
+    let mut walks = trees.map(|root| {
+        root.clone()
+            .into_owned()
+            .map(|root| unsafe { (root.min(), root.max()) })
+    });
+    let mut steps = 0;
+    loop {
+        for side in 0..2 {
+            let Some((node, last)) = &mut walks[side] else {
+                let mut lengths = [total - steps; 2];
+                lengths[side] = steps;
+                return lengths;
+            };
+            if node.ptr == last.ptr {
+                walks[side] = None;
+            } else {
+                *node = unsafe { node.next_unchecked() };
+            }
+        }
+        steps += 1;
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Detaches the whole tree, leaving the map empty but for its length.
+    fn take_tree(&mut self) -> Tree<K, V> {
+        let mut black_height = 0;
+        let mut node = self.root.clone();
+        while let Some(owned) = node.into_owned() {
+            black_height += owned.flag.is_black() as usize;
+            node = owned.next[0].clone();
+        }
+        self.first = NodeRef::none();
+        self.last = NodeRef::none();
+        self.finger = NodeRef::none();
+        Tree {
+            root: core::mem::replace(&mut self.root, NodeRef::none()),
+            black_height,
+        }
+    }
+    /// Makes `tree`, holding `length` nodes, the map's tree.
+    fn set_tree(&mut self, tree: Tree<K, V>, length: usize) {
+        self.root = tree.root;
+        self.length = length;
+        if let Some(root) = self.root.clone().into_owned() {
+            unsafe {
+                self.first = root.min().get_node_ref();
+                self.last = root.max().get_node_ref();
+            }
+        }
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Returns the first entry whose key does not satisfy `pred`, or `None` if
+    /// every key does.
+    ///
+    /// `pred` must hold for the keys up to some point and fail for the rest, as
+    /// with [`slice::partition_point`]. The search follows one path from the
+    /// root in `O(log n)` time, so `pred` may look at only part of the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// // (name, version) pairs, ordered by name and then version
+    /// let map = RBTreeMap::from([(("a", 1), 'x'), (("b", 1), 'y'), (("b", 4), 'z')]);
+    /// let first_b = map.partition_point(|&(name, _)| name < "b");
+    /// assert_eq!(first_b, Some((&("b", 1), &'y')));
+    /// assert_eq!(map.partition_point(|_| true), None);
+    /// ```
+    pub fn partition_point<F>(&self, pred: F) -> Option<(&K, &V)>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let [_, found] = self.raw_partition_point(pred);
+        found.map(OwnedNodeRef::into_ref_key_value)
+    }
+    /// Returns the last node satisfying `pred` and the first failing it.
+    pub(super) fn raw_partition_point<F>(&self, mut pred: F) -> [Option<OwnedNodeRef<K, V>>; 2]
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let mut found = [None, None];
+        let mut node = self.root.clone();
+        while let Some(owned) = node.into_owned() {
+            let passes = pred(&owned.key_value.0);
+            node = owned.next[passes as usize].clone();
+            found[!passes as usize] = Some(owned);
+        }
+        found
+    }
+    /// Splits the map at the [`partition_point`](Self::partition_point) of `pred`,
+    /// keeping the entries whose keys satisfy it and returning the rest.
+    ///
+    /// Splitting the tree takes `O(log n)` time. Setting both lengths then means
+    /// counting the smaller side, which adds `O(min(k, n - k))` for `k` entries
+    /// moved to the returned map. That map shares the allocator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.extend((0..10).map(|i| (i, i)));
+    /// let high = map.split_by(|&k| k < 7);
+    /// assert!(map.iter().map(|(k, _)| *k).eq(0..7));
+    /// assert!(high.iter().map(|(k, _)| *k).eq(7..10));
+    /// ```
+    pub fn split_by<F>(&mut self, mut pred: F) -> Self
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let mut rest = RBTreeMap::new_in(self.alloc.clone());
+        if self.is_empty() {
+            return rest;
+        }
+        let tree = self.take_tree();
+        let [kept, split_off] = split(tree, &mut |key: &K| !pred(key), &mut self.counters);
+        let [kept_length, length] = lengths([&kept.root, &split_off.root], self.length);
+        if !self.handles.is_empty() {
+            self.handles.release_subtree(&split_off.root);
+        }
+        rest.set_tree(split_off, length);
+        self.set_tree(kept, kept_length);
+        if !self.hooks.is_empty() {
+            for (k, v) in rest.iter() {
+                self.hooks.removed(k, v);
+            }
+        }
+        self.paranoid_shape();
+        rest.paranoid_shape();
+        rest
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Removes the entries whose keys lie in `range` and returns them as a map.
+    ///
+    /// The interval is cut out by splitting and rejoining the tree rather than
+    /// by removing entries one at a time, so this takes `O(log n + k)` time for
+    /// `k` removed entries. The nodes move to the returned map, which shares the allocator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.extend((0..10).map(|i| (i, i * 10)));
+    /// let removed = map.remove_range(3..7);
+    /// assert!(removed.into_iter().eq([(3, 30), (4, 40), (5, 50), (6, 60)]));
+    /// assert_eq!(map.len(), 6);
+    /// assert_eq!(map.first_key_value(), Some((&0, &0)));
+    /// assert!(map.remove_range(7..3).is_empty());
+    /// ```
+    pub fn remove_range<Q, R>(&mut self, range: R) -> Self
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        self.remove_range_by(range)
+    }
+    /// Like [`remove_range`](Self::remove_range), but compares the bounds through
+    /// [`Comparable`], so they may be a type the key does not borrow as, such as
+    /// [`Parts`](crate::collections::rbtree_map::Parts).
+    pub fn remove_range_by<Q, R>(&mut self, range: R) -> Self
+    where
+        Q: ?Sized + Comparable<K>,
+        R: RangeBounds<Q>,
+    {
+        let mut removed = RBTreeMap::new_in(self.alloc.clone());
+        if self.is_empty() {
+            return removed;
+        }
+        let tree = self.take_tree();
+        let [before, rest] = split(
+            tree,
+            &mut |key: &K| match range.start_bound() {
+                Bound::Included(start) => start.compare(key).is_le(),
+                Bound::Excluded(start) => start.compare(key).is_lt(),
+                Bound::Unbounded => true,
+            },
+            &mut self.counters,
+        );
+        let [inside, after] = split(
+            rest,
+            &mut |key: &K| match range.end_bound() {
+                Bound::Included(end) => end.compare(key).is_lt(),
+                Bound::Excluded(end) => end.compare(key).is_le(),
+                Bound::Unbounded => false,
+            },
+            &mut self.counters,
+        );
+        let kept = join_pair(before, after, &mut self.counters);
+        let [kept_length, length] = lengths([&kept.root, &inside.root], self.length);
+        if !self.handles.is_empty() {
+            self.handles.release_subtree(&inside.root);
+        }
+        removed.set_tree(inside, length);
+        self.set_tree(kept, kept_length);
+        if !self.hooks.is_empty() {
+            for (k, v) in removed.iter() {
+                self.hooks.removed(k, v);
+            }
+        }
+        self.paranoid();
+        removed.paranoid();
+        removed
+    }
+    /// Removes the entries whose keys lie in `range`.
+    ///
+    /// Takes `O(log n + k)` time for `k` removed entries, like
+    /// [`remove_range`](Self::remove_range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.extend((0..10).map(|i| (i, ())));
+    /// map.clear_range(..=2);
+    /// map.clear_range(8..);
+    /// assert!(map.iter().map(|(k, _)| *k).eq(3..8));
+    /// ```
+    pub fn clear_range<Q, R>(&mut self, range: R)
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        drop(self.remove_range(range));
+    }
+}
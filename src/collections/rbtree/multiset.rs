@@ -0,0 +1,390 @@
+use super::{
+    comparable::Comparable,
+    entry::{Entry, OccupiedEntry},
+    iter::Iter,
+    map::{NodeDesc, RBTreeMap},
+    node::{NodeRef, OwnedNodeRef},
+};
+use core::{borrow::Borrow, fmt::Debug, iter::FusedIterator};
+
+/// The value stored for each distinct element.
+#[derive(Debug, Clone, Copy)]
+struct Weight {
+    /// The occurrences of the element.
+    count: usize,
+    /// The occurrences of every element in the node's subtree.
+    total: usize,
+}
+
+/// Returns the occurrences in the subtree of `node`.
+fn total<T>(node: &NodeRef<T, Weight>) -> usize {
+    node.clone()
+        .into_owned()
+        .map_or(0, |node| node.key_value.1.total)
+}
+
+/// Recomputes the total of `node` from its count and its children's totals.
+fn refresh<T>(node: &NodeRef<T, Weight>) {
+    if let Some(mut node) = node.clone().into_owned() {
+        node.key_value.1.total =
+            node.key_value.1.count + total(&node.next[0]) + total(&node.next[1]);
+    }
+}
+
+/// Recomputes the totals of `node` and its ancestors after an insertion or
+/// removal below `node`.
+///
+/// Rebalancing only rotates nodes on the path from the changed node to the
+/// root and their children, so refreshing the children and grandchildren of
+/// each node on the path, bottom-up, catches every subtree that changed.
+fn refresh_path<T>(mut node: Option<OwnedNodeRef<T, Weight>>) {
+    while let Some(current) = node {
+        for child in &current.next {
+            if let Some(child) = child.clone().into_owned() {
+                refresh(&child.next[0]);
+                refresh(&child.next[1]);
+            }
+            refresh(child);
+        }
+        refresh(&current.get_node_ref());
+        node = (!current.flag.is_root()).then(|| current.parent.clone());
+    }
+}
+
+/// Returns the parent of the node that [`RBTreeMap::raw_remove`] frees when
+/// removing `node`, where rebalancing starts, or `None` if it frees the root.
+fn freed_parent<T>(node: &OwnedNodeRef<T, Weight>) -> Option<OwnedNodeRef<T, Weight>> {
+    let mut freed = node.clone();
+    // the entry is replaced by its successor, or by its only child
+    while let Some(right) = freed.next[1].clone().into_owned() {
+        freed = unsafe { right.min() };
+    }
+    if let Some(left) = freed.next[0].clone().into_owned() {
+        freed = left;
+    }
+    (!freed.flag.is_root()).then(|| freed.parent.clone())
+}
+
+/// An ordered multiset, keeping a count for each distinct element.
+///
+/// Elements live in an [`RBTreeMap`] from element to count, updated through its
+/// entry API. Each node also keeps the total count of its subtree, so finding
+/// the element at a position ([`nth`](Self::nth), [`median`](Self::median),
+/// [`quantile`](Self::quantile)) or the position of an element
+/// ([`rank`](Self::rank)) takes `O(log n)` time, with `n` the number of
+/// distinct elements.
+///
+/// Like [`count`](Self::count), every lookup takes any borrowed form of the
+/// element type whose ordering matches the ordering on the element type.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::RBTreeMultiSet;
+///
+/// let mut bag = RBTreeMultiSet::from([5, 1, 3, 3, 9]);
+/// assert_eq!(bag.len(), 5);
+/// assert_eq!(bag.count(&3), 2);
+/// assert_eq!(bag.nth(2), Some(&3));
+/// assert_eq!(bag.median(), Some(&3));
+/// assert_eq!(bag.rank(&5), 3);
+///
+/// bag.insert(3);
+/// assert!(bag.remove(&1));
+/// assert!(bag.iter().eq([(&3, 3), (&5, 1), (&9, 1)]));
+/// assert_eq!(bag.quantile(1.0), Some(&9));
+/// ```
+#[derive(Clone)]
+pub struct RBTreeMultiSet<T> {
+    map: RBTreeMap<T, Weight>,
+}
+
+impl<T> Default for RBTreeMultiSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Debug for RBTreeMultiSet<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> PartialEq for RBTreeMultiSet<T>
+where
+    T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.map.len() == other.map.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T> Eq for RBTreeMultiSet<T> where T: Eq {}
+
+impl<T> RBTreeMultiSet<T> {
+    /// Makes a new, empty `RBTreeMultiSet`.
+    pub fn new() -> Self {
+        Self {
+            map: RBTreeMap::new(),
+        }
+    }
+    /// Returns the number of elements, counting every occurrence.
+    pub fn len(&self) -> usize {
+        total(&self.map.root)
+    }
+    /// Returns the number of distinct elements.
+    pub fn distinct_len(&self) -> usize {
+        self.map.len()
+    }
+    /// Returns `true` if the multiset has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+    /// Removes every element.
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+    /// Gets an iterator over the distinct elements and their counts, sorted by
+    /// element.
+    pub fn iter(&self) -> MultiSetIter<'_, T> {
+        MultiSetIter {
+            inner: self.map.iter(),
+        }
+    }
+    /// Returns the smallest element.
+    pub fn first(&self) -> Option<&T> {
+        self.map.first_key_value().map(|(value, _)| value)
+    }
+    /// Returns the largest element.
+    pub fn last(&self) -> Option<&T> {
+        self.map.last_key_value().map(|(value, _)| value)
+    }
+    /// Returns the element at position `n` in sorted order, counting every
+    /// occurrence, or `None` if `n >= self.len()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMultiSet;
+    ///
+    /// let bag = RBTreeMultiSet::from(['b', 'a', 'b']);
+    /// assert_eq!(bag.nth(0), Some(&'a'));
+    /// assert_eq!(bag.nth(2), Some(&'b'));
+    /// assert_eq!(bag.nth(3), None);
+    /// ```
+    pub fn nth(&self, mut n: usize) -> Option<&T> {
+        let mut node = self.map.root.clone().into_owned()?;
+        loop {
+            let left = total(&node.next[0]);
+            if n < left {
+                node = node.next[0].get_owned();
+                continue;
+            }
+            n -= left;
+            if n < node.key_value.1.count {
+                return Some(&node.into_ref().key_value.0);
+            }
+            n -= node.key_value.1.count;
+            node = node.next[1].clone().into_owned()?;
+        }
+    }
+    /// Returns the lower median: the element at position `(len - 1) / 2`.
+    pub fn median(&self) -> Option<&T> {
+        self.nth(self.len().checked_sub(1)? / 2)
+    }
+    /// Returns the element at position `⌊q · (len - 1)⌋`, so `0.0` gives the
+    /// smallest element, `0.5` the lower median and `1.0` the largest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `q` is not between `0.0` and `1.0`.
+    pub fn quantile(&self, q: f64) -> Option<&T> {
+        assert!((0.0..=1.0).contains(&q), "quantile out of range: {q}");
+        let last = self.len().checked_sub(1)?;
+        self.nth((last as f64 * q) as usize)
+    }
+}
+
+impl<T> RBTreeMultiSet<T>
+where
+    T: Ord,
+{
+    /// Adds an occurrence of `value` and returns its new count.
+    pub fn insert(&mut self, value: T) -> usize {
+        self.insert_many(value, 1)
+    }
+    /// Adds `n` occurrences of `value` and returns its new count.
+    pub fn insert_many(&mut self, value: T, n: usize) -> usize {
+        if n == 0 {
+            return self.count(&value);
+        }
+        let node = match self.map.entry(value) {
+            Entry::Occupied(mut entry) => {
+                entry.get_mut().count += n;
+                entry.node()
+            }
+            Entry::Vacant(entry) => entry.insert_node(Weight { count: n, total: n }),
+        };
+        let count = node.key_value.1.count;
+        refresh_path(Some(node));
+        count
+    }
+    /// Returns the number of occurrences of `value`.
+    ///
+    /// The value may be any borrowed form of the element type, but the ordering
+    /// on the borrowed form *must* match the ordering on the element type.
+    pub fn count<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.map.get(value).map_or(0, |weight| weight.count)
+    }
+    /// Returns `true` if the multiset has an occurrence of `value`.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.map.contains_key(value)
+    }
+    /// Removes one occurrence of `value`, returning `true` if there was one.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.remove_many(value, 1) == 1
+    }
+    /// Removes up to `n` occurrences of `value` and returns how many were removed.
+    pub fn remove_many<Q>(&mut self, value: &Q, n: usize) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let NodeDesc::Found(node) = self.map.raw_search(value) else {
+            return 0;
+        };
+        let mut entry = OccupiedEntry::new(node.clone(), &mut self.map);
+        let count = entry.get().count;
+        if n < count {
+            entry.get_mut().count -= n;
+            refresh_path(Some(node));
+            n
+        } else {
+            let parent = freed_parent(&node);
+            entry.remove_entry();
+            refresh_path(parent);
+            count
+        }
+    }
+    /// Removes every occurrence of `value` and returns how many there were.
+    pub fn remove_all<Q>(&mut self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.remove_many(value, usize::MAX)
+    }
+    /// Returns the number of elements less than `value`, counting every
+    /// occurrence, which is the position of its first occurrence if present.
+    pub fn rank<Q>(&self, value: &Q) -> usize
+    where
+        T: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let mut rank = 0;
+        let mut node = self.map.root.clone();
+        while let Some(current) = node.into_owned() {
+            if value.compare(&current.key_value.0).is_gt() {
+                rank += total(&current.next[0]) + current.key_value.1.count;
+                node = current.next[1].clone();
+            } else {
+                node = current.next[0].clone();
+            }
+        }
+        rank
+    }
+}
+
+impl<T, const N: usize> From<[T; N]> for RBTreeMultiSet<T>
+where
+    T: Ord,
+{
+    fn from(arr: [T; N]) -> Self {
+        Self::from_iter(arr)
+    }
+}
+
+impl<T> FromIterator<T> for RBTreeMultiSet<T>
+where
+    T: Ord,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T> Extend<T> for RBTreeMultiSet<T>
+where
+    T: Ord,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a RBTreeMultiSet<T> {
+    type Item = (&'a T, usize);
+    type IntoIter = MultiSetIter<'a, T>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the distinct elements of an `RBTreeMultiSet` and their counts.
+///
+/// This `struct` is created by [`RBTreeMultiSet::iter`].
+pub struct MultiSetIter<'a, T> {
+    inner: Iter<'a, T, Weight>,
+}
+
+impl<T> Clone for MultiSetIter<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for MultiSetIter<'a, T> {
+    type Item = (&'a T, usize);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(value, weight)| (value, weight.count))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> DoubleEndedIterator for MultiSetIter<'_, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_back()
+            .map(|(value, weight)| (value, weight.count))
+    }
+}
+
+impl<T> ExactSizeIterator for MultiSetIter<'_, T> {}
+
+impl<T> FusedIterator for MultiSetIter<'_, T> {}
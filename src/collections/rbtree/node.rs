@@ -1,7 +1,10 @@
-use super::flag::{toggle_rela, Flag, LEFT, RIGHT};
+use super::{
+    comparable::Comparable,
+    flag::{toggle_rela, Flag, LEFT, RIGHT},
+    stats::Counters,
+};
 use crate::alloc::{handle_alloc_error, Allocator};
 use core::alloc::Layout;
-use core::borrow::Borrow;
 use core::fmt::{Debug, Display};
 use core::ops::{Deref, DerefMut};
 use core::ptr::NonNull;
@@ -26,6 +29,16 @@ pub struct Node<K, V> {
     pub flag: Flag,
 }
 
+// Handles keep their slots in a side table, so a node is no larger for them.
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    use core::mem::size_of;
+    assert!(size_of::<Node<u64, u64>>() == 48);
+    assert!(size_of::<Node<u32, u32>>() == 40);
+    assert!(size_of::<Node<u8, ()>>() == 32);
+    assert!(size_of::<Node<u32, ()>>() == 32);
+};
+
 impl<K, V> Debug for Node<K, V>
 where
     K: Debug,
@@ -73,17 +86,6 @@ where
     }
 }
 
-impl<K, V> Node<K, V>
-where
-    K: Clone,
-    V: Clone,
-{
-    pub fn init_from(&mut self, source: &Self) {
-        self.key_value = source.key_value.clone();
-        self.flag = source.flag;
-    }
-}
-
 impl<K, V> Node<K, V> {
     pub fn new_in<A>(alloc: A) -> NonNull<Self>
     where
@@ -246,14 +248,13 @@ impl<K, V> OwnedNodeRef<K, V> {
     }
     pub fn search<Q>(&self, key: &Q) -> SearchResult<K, V>
     where
-        K: Borrow<Q>,
-        Q: ?Sized + Ord,
+        Q: ?Sized + Comparable<K>,
     {
         let mut cur;
         let mut last = self.clone();
         let mut rela;
         loop {
-            rela = match key.borrow().cmp(last.key_value.0.borrow()) {
+            rela = match key.compare(&last.key_value.0) {
                 core::cmp::Ordering::Equal => return SearchResult::Found(last.clone()),
                 core::cmp::Ordering::Less => {
                     cur = last.next[0].clone();
@@ -270,7 +271,10 @@ impl<K, V> OwnedNodeRef<K, V> {
             last = cur.into_owned().unwrap();
         }
     }
-    pub fn rest_double_red_adjust(&mut self) -> Option<OwnedNodeRef<K, V>> {
+    pub(super) fn rest_double_red_adjust(
+        &mut self,
+        counters: &mut Counters,
+    ) -> Option<OwnedNodeRef<K, V>> {
         let mut new_root = None;
         let mut child = self.clone();
         let mut parent = child.parent.clone();
@@ -283,6 +287,7 @@ impl<K, V> OwnedNodeRef<K, V> {
             let grela = gparent.flag.rela();
             let crela = child.flag.rela();
             if child.flag.rela() != prela {
+                counters.rotate(2);
                 if gparent.flag.is_root() {
                     child.flag.set_root();
                     new_root = Some(child.clone());
@@ -295,6 +300,7 @@ impl<K, V> OwnedNodeRef<K, V> {
                 child.set_child(parent.clone(), prela);
                 parent = child;
             } else {
+                counters.rotate(1);
                 if gparent.flag.is_root() {
                     parent.flag.set_root();
                     new_root = Some(parent.clone());
@@ -306,11 +312,12 @@ impl<K, V> OwnedNodeRef<K, V> {
                 parent.set_child(gparent, toggle_grela);
             }
         } else {
+            counters.recolor();
             uncle.flag.set_black();
             if gparent.flag.is_root() {
                 gparent.flag.set_black();
             } else if gparent.parent.flag.is_red() {
-                if let Some(nr) = gparent.rest_double_red_adjust() {
+                if let Some(nr) = gparent.rest_double_red_adjust(counters) {
                     new_root = Some(nr);
                 }
             }
@@ -318,7 +325,10 @@ impl<K, V> OwnedNodeRef<K, V> {
         parent.flag.set_black();
         new_root
     }
-    pub fn double_red_adjust(&mut self) -> Option<OwnedNodeRef<K, V>> {
+    pub(super) fn double_red_adjust(
+        &mut self,
+        counters: &mut Counters,
+    ) -> Option<OwnedNodeRef<K, V>> {
         let mut new_root = None;
         let mut child = self.clone();
         let mut parent = child.parent.clone();
@@ -330,6 +340,7 @@ impl<K, V> OwnedNodeRef<K, V> {
             let crela = child.flag.rela();
             let grela = gparent.flag.rela();
             if crela != prela {
+                counters.rotate(2);
                 if gparent.flag.is_root() {
                     child.flag.set_root();
                     new_root = Some(child.clone());
@@ -342,6 +353,7 @@ impl<K, V> OwnedNodeRef<K, V> {
                 parent.next[crela as usize] = NodeRef::none();
                 gparent.next[prela as usize] = NodeRef::none();
             } else {
+                counters.rotate(1);
                 if gparent.flag.is_root() {
                     parent.flag.set_root();
                     new_root = Some(parent.clone());
@@ -353,19 +365,24 @@ impl<K, V> OwnedNodeRef<K, V> {
                 parent.set_child(gparent, toggle_rela(crela));
             }
         } else {
+            counters.recolor();
             uncle.into_owned().unwrap().flag.set_black();
             parent.flag.set_black();
             if gparent.flag.is_root() {
                 gparent.flag.set_black();
             } else if gparent.parent.flag.is_red() {
-                if let Some(nr) = gparent.rest_double_red_adjust() {
+                if let Some(nr) = gparent.rest_double_red_adjust(counters) {
                     new_root = Some(nr);
                 }
             }
         }
         new_root
     }
-    pub(super) fn rasie(&mut self, rela: u8) -> Option<OwnedNodeRef<K, V>> {
+    pub(super) fn rasie(
+        &mut self,
+        rela: u8,
+        counters: &mut Counters,
+    ) -> Option<OwnedNodeRef<K, V>> {
         let mut new_root = None;
         let toggle_rela = toggle_rela(rela);
         let mut parent = self.clone();
@@ -375,6 +392,7 @@ impl<K, V> OwnedNodeRef<K, V> {
             let mut rnephew = brother.next[toggle_rela as usize].get_owned();
             let mut lnephew = brother.next[rela as usize].get_owned();
             if rnephew.flag.is_red() {
+                counters.rotate(1);
                 if parent.flag.is_root() {
                     brother.flag.set_root();
                     new_root = Some(brother.clone());
@@ -387,6 +405,7 @@ impl<K, V> OwnedNodeRef<K, V> {
                 parent.set_child(lnephew, toggle_rela);
                 brother.set_child(parent, rela);
             } else if lnephew.flag.is_red() {
+                counters.rotate(2);
                 if parent.flag.is_root() {
                     lnephew.flag.set_root();
                     new_root = Some(lnephew.clone());
@@ -400,16 +419,18 @@ impl<K, V> OwnedNodeRef<K, V> {
                 lnephew.set_child(parent, rela);
                 lnephew.set_child(brother, toggle_rela);
             } else {
+                counters.recolor();
                 brother.flag.set_red();
                 if parent.flag.is_red() {
                     parent.flag.set_black();
                 } else if !parent.flag.is_root() {
-                    if let Some(nr) = parent.parent.rasie(prela) {
+                    if let Some(nr) = parent.parent.rasie(prela, counters) {
                         new_root = Some(nr);
                     }
                 }
             }
         } else {
+            counters.rotate(1);
             if parent.flag.is_root() {
                 brother.flag.set_root();
                 new_root = Some(brother.clone());
@@ -421,7 +442,7 @@ impl<K, V> OwnedNodeRef<K, V> {
             parent.set_child(brother.next[rela as usize].get_owned(), toggle_rela);
             brother.set_child(parent.clone(), rela);
 
-            if let Some(nr) = parent.rasie(rela) {
+            if let Some(nr) = parent.rasie(rela, counters) {
                 new_root = Some(nr);
             }
         }
@@ -0,0 +1,219 @@
+use super::{map::RBTreeMap, transaction::Journal};
+use crate::alloc::{Allocator, Box};
+
+/// Everything that wants to hear about changes to a map.
+///
+/// The listeners live behind a pointer allocated from the map's allocator on the
+/// first [`set_observer`](RBTreeMap::set_observer) or [`begin`](RBTreeMap::begin),
+/// and freed again once both are gone, so a map nobody listens to carries one
+/// null pointer and pays one branch on it per hook.
+pub(super) struct Hooks<K, V, A: Allocator>(Option<Box<Listeners<K, V, A>, A>>);
+
+pub(super) struct Listeners<K, V, A: Allocator> {
+    pub(super) observer: Option<Box<dyn Observer<K, V>, A>>,
+    pub(super) journal: Option<Journal<K, V, A>>,
+}
+
+impl<K, V, A: Allocator> Observer<K, V> for Listeners<K, V, A> {
+    fn on_insert(&mut self, key: &K, value: &V) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_insert(key, value);
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            journal.inserted(key);
+        }
+    }
+    fn on_update(&mut self, key: &K, old: &V, new: &V) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_update(key, old, new);
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            journal.updated(key, old);
+        }
+    }
+    fn on_remove(&mut self, key: &K, value: &V) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_remove(key, value);
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            journal.removed(key, value);
+        }
+    }
+    fn on_get_mut(&mut self, key: &K, value: &V) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.on_get_mut(key, value);
+        }
+        if let Some(journal) = self.journal.as_mut() {
+            journal.updated(key, value);
+        }
+    }
+}
+
+impl<K, V, A: Allocator + Clone> Hooks<K, V, A> {
+    pub(super) const fn new() -> Self {
+        Self(None)
+    }
+    #[inline(always)]
+    pub(super) fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+    /// Returns the bytes the listeners take from the map's allocator.
+    pub(super) fn allocated_bytes(&self) -> usize {
+        self.0.as_ref().map_or(0, |listeners| {
+            core::mem::size_of::<Listeners<K, V, A>>()
+                + listeners
+                    .observer
+                    .as_ref()
+                    .map_or(0, |observer| core::mem::size_of_val(&**observer))
+                + listeners
+                    .journal
+                    .as_ref()
+                    .map_or(0, Journal::allocated_bytes)
+        })
+    }
+    /// Returns the listeners, allocating them from `alloc` if there are none yet.
+    pub(super) fn listeners(&mut self, alloc: &A) -> &mut Listeners<K, V, A> {
+        self.0.get_or_insert_with(|| {
+            Box::new_in(
+                Listeners {
+                    observer: None,
+                    journal: None,
+                },
+                alloc.clone(),
+            )
+        })
+    }
+    /// Returns the listeners as an observer of every change, if there are any.
+    #[inline(always)]
+    pub(super) fn as_observer(&mut self) -> Option<&mut dyn Observer<K, V>> {
+        match self.0.as_deref_mut() {
+            Some(listeners) => Some(listeners),
+            None => None,
+        }
+    }
+    pub(super) fn take_observer(&mut self) -> Option<Box<dyn Observer<K, V>, A>> {
+        let observer = self.0.as_mut()?.observer.take();
+        self.free_if_unused();
+        observer
+    }
+    pub(super) fn take_journal(&mut self) -> Option<Journal<K, V, A>> {
+        let journal = self.0.as_mut()?.journal.take();
+        self.free_if_unused();
+        journal
+    }
+    fn free_if_unused(&mut self) {
+        if self
+            .0
+            .as_ref()
+            .is_some_and(|l| l.observer.is_none() && l.journal.is_none())
+        {
+            self.0 = None;
+        }
+    }
+    #[inline(always)]
+    pub(super) fn inserted(&mut self, key: &K, value: &V) {
+        if let Some(listeners) = self.0.as_mut() {
+            listeners.on_insert(key, value);
+        }
+    }
+    #[inline(always)]
+    pub(super) fn updated(&mut self, key: &K, old: &V, new: &V) {
+        if let Some(listeners) = self.0.as_mut() {
+            listeners.on_update(key, old, new);
+        }
+    }
+    #[inline(always)]
+    pub(super) fn removed(&mut self, key: &K, value: &V) {
+        if let Some(listeners) = self.0.as_mut() {
+            listeners.on_remove(key, value);
+        }
+    }
+    #[inline(always)]
+    pub(super) fn get_mut(&mut self, key: &K, value: &V) {
+        if let Some(listeners) = self.0.as_mut() {
+            listeners.on_get_mut(key, value);
+        }
+    }
+}
+
+/// Receives a notification for every change made to an [`RBTreeMap`].
+///
+/// Install one with [`RBTreeMap::set_observer`]. All methods do nothing by default.
+///
+/// # Examples
+///
+/// ```
+/// use std::{cell::RefCell, rc::Rc};
+/// use xsl::collections::{rbtree_map::Observer, RBTreeMap};
+///
+/// struct Log(Rc<RefCell<Vec<String>>>);
+///
+/// impl Observer<i32, &'static str> for Log {
+///     fn on_insert(&mut self, key: &i32, value: &&'static str) {
+///         self.0.borrow_mut().push(format!("+{key}={value}"));
+///     }
+///     fn on_update(&mut self, key: &i32, _old: &&'static str, new: &&'static str) {
+///         self.0.borrow_mut().push(format!("~{key}={new}"));
+///     }
+///     fn on_remove(&mut self, key: &i32, _value: &&'static str) {
+///         self.0.borrow_mut().push(format!("-{key}"));
+///     }
+/// }
+///
+/// let log = Rc::new(RefCell::new(Vec::new()));
+/// let mut map = RBTreeMap::new();
+/// map.set_observer(Log(log.clone()));
+/// map.insert(1, "a");
+/// map.insert(1, "b");
+/// map.entry(2).or_insert("c");
+/// map.pop_first();
+/// assert_eq!(*log.borrow(), ["+1=a", "~1=b", "+2=c", "-1"]);
+/// ```
+pub trait Observer<K, V> {
+    /// Called after a new entry was inserted.
+    fn on_insert(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+    /// Called after the value of an existing entry was replaced.
+    fn on_update(&mut self, key: &K, old: &V, new: &V) {
+        let _ = (key, old, new);
+    }
+    /// Called before an entry is removed.
+    fn on_remove(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+    /// Called before the map hands out a mutable reference to `value`,
+    /// for example from `get_mut`, `iter_mut` or `values_mut`.
+    ///
+    /// The map cannot see what is written through that reference, so an observer
+    /// that needs the new value should treat the key as dirty and read it back later.
+    fn on_get_mut(&mut self, key: &K, value: &V) {
+        let _ = (key, value);
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Installs an observer notified of every later insert, update and removal,
+    /// replacing any previous one.
+    ///
+    /// The observer stays with this map: clones of the map start without one,
+    /// and dropping the map or turning it into an iterator does not report removals.
+    /// The observer is boxed in the map's allocator. Without an observer or a
+    /// running transaction the map holds a null pointer in its place and the
+    /// hooks cost a branch each.
+    pub fn set_observer<O>(&mut self, observer: O)
+    where
+        O: Observer<K, V> + 'static,
+    {
+        let observer: Box<dyn Observer<K, V>, A> =
+            allocator_api2::unsize_box!(Box::new_in(observer, self.alloc.clone()));
+        self.hooks.listeners(&self.alloc).observer = Some(observer);
+    }
+    /// Removes the observer from the map and returns it.
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer<K, V>, A>> {
+        self.hooks.take_observer()
+    }
+}
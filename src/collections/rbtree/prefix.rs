@@ -0,0 +1,114 @@
+extern crate alloc;
+
+use super::{iter::Range, map::RBTreeMap};
+use crate::alloc::Allocator;
+use alloc::{string::String, vec::Vec};
+use core::{borrow::Borrow, ops::Bound};
+
+/// Key types that can be queried by prefix, such as [`str`] and byte slices.
+pub trait Prefix: Ord {
+    /// The owned form of the bound returned by [`prefix_end`](Prefix::prefix_end).
+    type Owned: Borrow<Self>;
+    /// Returns the least value greater than every value starting with `self`,
+    /// or `None` if there is none.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::rbtree_map::Prefix;
+    ///
+    /// assert_eq!("foo/".prefix_end().as_deref(), Some("foo0"));
+    /// assert_eq!("a\u{10FFFF}".prefix_end().as_deref(), Some("b"));
+    /// assert_eq!(b"\x01\xFF"[..].prefix_end().as_deref(), Some(&b"\x02"[..]));
+    /// assert_eq!(b"\xFF"[..].prefix_end(), None);
+    /// ```
+    fn prefix_end(&self) -> Option<Self::Owned>;
+}
+
+impl Prefix for str {
+    type Owned = String;
+    fn prefix_end(&self) -> Option<String> {
+        // values starting with `self` run up to the next value of its last
+        // char that is not `char::MAX`
+        let mut end = String::from(self.trim_end_matches(char::MAX));
+        let last = end.pop()?;
+        let next = match last {
+            '\u{D7FF}' => '\u{E000}',
+            last => char::from_u32(last as u32 + 1).unwrap(),
+        };
+        end.push(next);
+        Some(end)
+    }
+}
+
+impl Prefix for [u8] {
+    type Owned = Vec<u8>;
+    fn prefix_end(&self) -> Option<Vec<u8>> {
+        let len = self.iter().rposition(|&byte| byte != u8::MAX)? + 1;
+        let mut end = self[..len].to_vec();
+        end[len - 1] += 1;
+        Some(end)
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Gets a double-ended iterator over the entries whose keys start with
+    /// `prefix`, sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let map = RBTreeMap::from([
+    ///     ("foo".to_string(), 1),
+    ///     ("foo/a".to_string(), 2),
+    ///     ("foo/b".to_string(), 3),
+    ///     ("foo0".to_string(), 4),
+    /// ]);
+    /// let keys: Vec<_> = map.prefix_range("foo/").map(|(k, _)| k.as_str()).collect();
+    /// assert_eq!(keys, ["foo/a", "foo/b"]);
+    ///
+    /// let bytes = RBTreeMap::from([(vec![1, 255], 'a'), (vec![1, 255, 0], 'b'), (vec![2], 'c')]);
+    /// assert_eq!(bytes.prefix_range(&[1, 255][..]).count(), 2);
+    /// ```
+    pub fn prefix_range<P>(&self, prefix: &P) -> Range<'_, K, V>
+    where
+        K: Borrow<P>,
+        P: ?Sized + Prefix,
+    {
+        match prefix.prefix_end() {
+            Some(end) => self.range((Bound::Included(prefix), Bound::Excluded(end.borrow()))),
+            None => self.range((Bound::Included(prefix), Bound::Unbounded)),
+        }
+    }
+    /// Removes the entries whose keys start with `prefix` and returns them as a
+    /// map, in `O(log n + k)` time like [`remove_range`](Self::remove_range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([("a/1", 1), ("a/2", 2), ("b/1", 3)]);
+    /// let removed = map.remove_prefix("a/");
+    /// assert_eq!(removed.len(), 2);
+    /// assert!(map.into_iter().eq([("b/1", 3)]));
+    /// ```
+    pub fn remove_prefix<P>(&mut self, prefix: &P) -> Self
+    where
+        K: Borrow<P>,
+        P: ?Sized + Prefix,
+    {
+        match prefix.prefix_end() {
+            Some(end) => {
+                self.remove_range((Bound::Included(prefix), Bound::Excluded(end.borrow())))
+            }
+            None => self.remove_range((Bound::Included(prefix), Bound::Unbounded)),
+        }
+    }
+}
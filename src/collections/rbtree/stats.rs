@@ -0,0 +1,120 @@
+use super::{
+    map::RBTreeMap,
+    node::{Node, NodeRef},
+};
+use crate::alloc::Allocator;
+
+/// A snapshot of the shape of an [`RBTreeMap`], returned by [`RBTreeMap::stats`].
+///
+/// The rebalancing counters are only present with the `stats` feature, and count
+/// the work done since the map was created.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TreeStats {
+    /// The number of nodes, which is also the number of entries.
+    pub len: usize,
+    /// The number of nodes on the longest path from the root to a leaf.
+    pub height: usize,
+    /// The number of black nodes on every path from the root to a leaf.
+    pub black_height: usize,
+    /// The bytes currently allocated through the map's allocator: the nodes, and
+    /// the handle table, observer and undo log once the map has them.
+    pub allocated_bytes: usize,
+    /// Single rotations done while rebalancing; a double rotation counts twice.
+    #[cfg(feature = "stats")]
+    pub rotations: u64,
+    /// Rebalancing steps resolved by recolouring alone, without a rotation.
+    #[cfg(feature = "stats")]
+    pub recolorings: u64,
+}
+
+/// Rebalancing counters threaded through the fix-up routines.
+///
+/// Without the `stats` feature this is a zero-sized type and counting compiles away.
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct Counters {
+    #[cfg(feature = "stats")]
+    rotations: u64,
+    #[cfg(feature = "stats")]
+    recolorings: u64,
+}
+
+impl Counters {
+    pub(super) const fn new() -> Self {
+        Self {
+            #[cfg(feature = "stats")]
+            rotations: 0,
+            #[cfg(feature = "stats")]
+            recolorings: 0,
+        }
+    }
+    #[inline(always)]
+    pub(super) fn rotate(&mut self, count: u64) {
+        #[cfg(feature = "stats")]
+        {
+            self.rotations += count;
+        }
+        #[cfg(not(feature = "stats"))]
+        let _ = count;
+    }
+    #[inline(always)]
+    pub(super) fn recolor(&mut self) {
+        #[cfg(feature = "stats")]
+        {
+            self.recolorings += 1;
+        }
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    pub(super) fn allocated_bytes(&self) -> usize {
+        self.length * core::mem::size_of::<Node<K, V>>()
+            + self.hooks.allocated_bytes()
+            + self.handles.allocated_bytes()
+    }
+    /// Returns the shape of the tree and, with the `stats` feature, how much
+    /// rebalancing it has done.
+    ///
+    /// Measuring the height walks the whole tree, so this takes `O(n)` time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.extend((0..1000).map(|i| (i, i)));
+    /// let stats = map.stats();
+    /// assert_eq!(stats.len, 1000);
+    /// assert!(stats.height <= 2 * stats.black_height);
+    /// assert!(stats.allocated_bytes >= 1000 * core::mem::size_of::<(i32, i32)>());
+    /// ```
+    pub fn stats(&self) -> TreeStats {
+        fn height<K, V>(node: &NodeRef<K, V>) -> usize {
+            match node.clone().into_owned() {
+                Some(node) => 1 + height(&node.next[0]).max(height(&node.next[1])),
+                None => 0,
+            }
+        }
+        let mut black_height = 0;
+        let mut node = self.root.clone();
+        while let Some(owned) = node.into_owned() {
+            if owned.flag.is_black() {
+                black_height += 1;
+            }
+            node = owned.next[0].clone();
+        }
+        TreeStats {
+            len: self.length,
+            height: height(&self.root),
+            black_height,
+            allocated_bytes: self.allocated_bytes(),
+            #[cfg(feature = "stats")]
+            rotations: self.counters.rotations,
+            #[cfg(feature = "stats")]
+            recolorings: self.counters.recolorings,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use super::{entry::Entry, map::RBTreeMap};
+use crate::alloc::{Allocator, Vec};
+use core::ops::{Deref, DerefMut};
+
+enum Undo<K, V> {
+    Inserted(K),
+    Updated(K, V),
+    Removed(K, V),
+}
+
+/// The undo log of the running transactions of a map.
+///
+/// The clone functions are captured by [`RBTreeMap::begin`], where `K: Clone` and
+/// `V: Clone` are known, so the removal path can record values without those bounds.
+pub(super) struct Journal<K, V, A: Allocator> {
+    log: Vec<Undo<K, V>, A>,
+    clone_key: fn(&K) -> K,
+    clone_value: fn(&V) -> V,
+}
+
+impl<K, V, A: Allocator> Journal<K, V, A> {
+    pub(super) fn allocated_bytes(&self) -> usize {
+        self.log.capacity() * core::mem::size_of::<Undo<K, V>>()
+    }
+    #[inline]
+    pub(super) fn inserted(&mut self, key: &K) {
+        self.log.push(Undo::Inserted((self.clone_key)(key)));
+    }
+    #[inline]
+    pub(super) fn updated(&mut self, key: &K, old: &V) {
+        self.log.push(Undo::Updated(
+            (self.clone_key)(key),
+            (self.clone_value)(old),
+        ));
+    }
+    #[inline]
+    pub(super) fn removed(&mut self, key: &K, value: &V) {
+        self.log.push(Undo::Removed(
+            (self.clone_key)(key),
+            (self.clone_value)(value),
+        ));
+    }
+}
+
+/// A guard recording every change made to an [`RBTreeMap`] so it can be undone.
+///
+/// The guard dereferences to the map, so the whole map API is available while it
+/// is alive. Changes are kept by [`commit`] and undone by [`rollback`]; dropping the
+/// guard without calling either rolls back. Calling [`begin`] on a guard starts a
+/// nested transaction, which acts as a savepoint inside the outer one.
+///
+/// Assigning a whole new map through the guard, as in `*tx = RBTreeMap::new()`,
+/// ends the recording: the undo log belongs to the replaced map, so neither this
+/// transaction nor an enclosing one can roll back past that point.
+///
+/// [`commit`]: Transaction::commit
+/// [`rollback`]: Transaction::rollback
+/// [`begin`]: RBTreeMap::begin
+pub struct Transaction<'a, K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    map: &'a mut RBTreeMap<K, V, A>,
+    savepoint: usize,
+    outermost: bool,
+    finished: bool,
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord + Clone,
+    V: Clone,
+    A: Allocator + Clone,
+{
+    /// Starts a transaction over the map.
+    ///
+    /// Inserts, overwrites, removals and values handed out as `&mut V` are recorded,
+    /// cloning the keys and values needed to undo them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([(1, "a"), (2, "b")]);
+    /// let mut tx = map.begin();
+    /// tx.insert(3, "c");
+    /// tx.remove(&1);
+    /// {
+    ///     let mut savepoint = tx.begin();
+    ///     savepoint.insert(2, "x");
+    ///     savepoint.rollback();
+    /// }
+    /// assert_eq!(tx[&2], "b");
+    /// tx.rollback();
+    /// assert_eq!(map, RBTreeMap::from([(1, "a"), (2, "b")]));
+    /// ```
+    pub fn begin(&mut self) -> Transaction<'_, K, V, A> {
+        let listeners = self.hooks.listeners(&self.alloc);
+        let outermost = listeners.journal.is_none();
+        let journal = listeners.journal.get_or_insert_with(|| Journal {
+            log: Vec::new_in(self.alloc.clone()),
+            clone_key: K::clone,
+            clone_value: V::clone,
+        });
+        Transaction {
+            savepoint: journal.log.len(),
+            map: self,
+            outermost,
+            finished: false,
+        }
+    }
+}
+
+impl<K, V, A> Transaction<'_, K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Keeps every change made since the transaction began.
+    ///
+    /// Committing a nested transaction hands its changes to the enclosing one,
+    /// which can still roll them back.
+    pub fn commit(mut self) {
+        self.finish(false);
+    }
+    /// Undoes every change made since the transaction began.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::from([(1, 10)]);
+    /// let mut tx = map.begin();
+    /// *tx.get_mut(&1).unwrap() += 5;
+    /// tx.insert(2, 20);
+    /// tx.rollback();
+    /// assert_eq!(map, RBTreeMap::from([(1, 10)]));
+    /// ```
+    pub fn rollback(mut self) {
+        self.finish(true);
+    }
+    fn finish(&mut self, rollback: bool) {
+        self.finished = true;
+        // replacing the whole map through the guard drops the journal with the
+        // old map, leaving nothing to undo
+        let Some(mut journal) = self.map.hooks.take_journal() else {
+            return;
+        };
+        if rollback {
+            while journal.log.len() > self.savepoint {
+                match journal.log.pop().unwrap() {
+                    Undo::Inserted(key) => {
+                        self.map.remove(&key);
+                    }
+                    Undo::Updated(key, value) => {
+                        if let Entry::Occupied(mut entry) = self.map.entry(key) {
+                            entry.insert(value);
+                        }
+                    }
+                    Undo::Removed(key, value) => {
+                        self.map.insert(key, value);
+                    }
+                }
+            }
+        }
+        if !self.outermost {
+            self.map.hooks.listeners(&self.map.alloc).journal = Some(journal);
+        }
+    }
+}
+
+impl<K, V, A> Drop for Transaction<'_, K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finish(true);
+        }
+    }
+}
+
+impl<K, V, A> Deref for Transaction<'_, K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    type Target = RBTreeMap<K, V, A>;
+    fn deref(&self) -> &Self::Target {
+        self.map
+    }
+}
+
+impl<K, V, A> DerefMut for Transaction<'_, K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.map
+    }
+}
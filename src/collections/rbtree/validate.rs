@@ -0,0 +1,195 @@
+use super::{
+    flag::ROOT,
+    map::RBTreeMap,
+    node::{Node, NodeRef},
+};
+use crate::alloc::Allocator;
+use core::fmt::Display;
+
+/// An invariant of an [`RBTreeMap`] found broken by [`RBTreeMap::validate`].
+///
+/// Every variant except `Length` carries the key of the offending node.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InvariantViolation<'a, K> {
+    /// The root node is red.
+    RedRoot { key: &'a K },
+    /// A red node has a red parent; `key` is the child's.
+    RedRed { key: &'a K },
+    /// The two subtrees of a node hold different numbers of black nodes.
+    BlackHeight { key: &'a K },
+    /// A key is not greater than the key before it in order.
+    Order { key: &'a K },
+    /// A node's parent pointer or side does not match the node linking to it.
+    ParentPointer { key: &'a K },
+    /// The stored length differs from the number of nodes in the tree.
+    Length { expected: usize, found: usize },
+    /// The cached first or last node is not the tree's smallest or largest;
+    /// `key` is the cached node's.
+    CachedEnd { key: &'a K },
+}
+
+impl<'a, K> InvariantViolation<'a, K> {
+    /// Returns the key of the offending node, if the violation has one.
+    pub fn key(&self) -> Option<&'a K> {
+        match *self {
+            Self::RedRoot { key }
+            | Self::RedRed { key }
+            | Self::BlackHeight { key }
+            | Self::Order { key }
+            | Self::ParentPointer { key }
+            | Self::CachedEnd { key } => Some(key),
+            Self::Length { .. } => None,
+        }
+    }
+}
+
+impl<K> Display for InvariantViolation<'_, K> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::RedRoot { .. } => write!(f, "root is red"),
+            Self::RedRed { .. } => write!(f, "red node has a red parent"),
+            Self::BlackHeight { .. } => write!(f, "subtrees have different black heights"),
+            Self::Order { .. } => write!(f, "keys are out of order"),
+            Self::ParentPointer { .. } => write!(f, "parent pointer does not match"),
+            Self::Length { expected, found } => {
+                write!(f, "length is {expected} but the tree has {found} nodes")
+            }
+            Self::CachedEnd { .. } => write!(f, "cached first or last node is stale"),
+        }
+    }
+}
+
+struct Walk<'a, K> {
+    less: Option<fn(&K, &K) -> bool>,
+    prev: Option<&'a K>,
+    count: usize,
+}
+
+impl<'a, K> Walk<'a, K> {
+    /// Checks the subtree under `node` in order, returning its black height.
+    fn subtree<V>(
+        &mut self,
+        node: &NodeRef<K, V>,
+        parent: Option<&'a Node<K, V>>,
+        rela: u8,
+    ) -> Result<usize, InvariantViolation<'a, K>> {
+        let Some(owned) = node.clone().into_owned() else {
+            return Ok(0);
+        };
+        let node: &'a Node<K, V> = owned.into_ref();
+        let key = &node.key_value.0;
+        let linked = match parent {
+            Some(parent) => core::ptr::eq(node.parent.ptr.as_ptr(), parent),
+            None => true,
+        };
+        if !linked || node.flag.rela() != rela {
+            return Err(InvariantViolation::ParentPointer { key });
+        }
+        if node.flag.is_red() && parent.is_some_and(|parent| parent.flag.is_red()) {
+            return Err(InvariantViolation::RedRed { key });
+        }
+        let left = self.subtree(&node.next[0], Some(node), 0)?;
+        if let (Some(less), Some(prev)) = (self.less, self.prev) {
+            if !less(prev, key) {
+                return Err(InvariantViolation::Order { key });
+            }
+        }
+        self.prev = Some(key);
+        self.count += 1;
+        let right = self.subtree(&node.next[1], Some(node), 1)?;
+        if left != right {
+            return Err(InvariantViolation::BlackHeight { key });
+        }
+        Ok(left + node.flag.is_black() as usize)
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    A: Allocator + Clone,
+{
+    /// Checks the invariants, comparing keys only when `less` is given,
+    /// so callers without `K: Ord` can still check the shape.
+    pub(super) fn raw_validate(
+        &self,
+        less: Option<fn(&K, &K) -> bool>,
+    ) -> Result<(), InvariantViolation<'_, K>> {
+        if let Some(root) = self.root.clone().into_owned() {
+            for (end, expected) in [
+                (&self.first, unsafe { root.min() }),
+                (&self.last, unsafe { root.max() }),
+            ] {
+                if end.ptr != Some(expected.ptr) {
+                    return Err(match end.clone().into_owned() {
+                        Some(end) => InvariantViolation::CachedEnd {
+                            key: &end.into_ref().key_value.0,
+                        },
+                        None => InvariantViolation::CachedEnd {
+                            key: &expected.into_ref().key_value.0,
+                        },
+                    });
+                }
+            }
+            let root = root.into_ref();
+            if root.flag.is_red() {
+                return Err(InvariantViolation::RedRoot {
+                    key: &root.key_value.0,
+                });
+            }
+        }
+        let mut walk = Walk {
+            less,
+            prev: None,
+            count: 0,
+        };
+        walk.subtree(&self.root, None, ROOT)?;
+        if walk.count != self.length {
+            return Err(InvariantViolation::Length {
+                expected: self.length,
+                found: walk.count,
+            });
+        }
+        Ok(())
+    }
+    /// With the `paranoid` feature, panics unless the tree has a valid shape.
+    #[inline(always)]
+    pub(super) fn paranoid_shape(&self) {
+        #[cfg(feature = "paranoid")]
+        if let Err(violation) = self.raw_validate(None) {
+            panic!("RBTreeMap invariant violated: {violation}");
+        }
+    }
+}
+
+impl<K, V, A> RBTreeMap<K, V, A>
+where
+    K: Ord,
+    A: Allocator + Clone,
+{
+    /// Checks that the map is a valid red-black tree, returning the first
+    /// broken invariant found.
+    ///
+    /// This walks the whole tree and takes `O(n)` time. It can only fail if unsafe
+    /// code corrupted the map or `K`'s `Ord` implementation is inconsistent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::RBTreeMap;
+    ///
+    /// let mut map = RBTreeMap::new();
+    /// map.extend((0..100).map(|i| (i, i)));
+    /// assert_eq!(map.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), InvariantViolation<'_, K>> {
+        self.raw_validate(Some(|a, b| a < b))
+    }
+    /// With the `paranoid` feature, panics unless [`validate`](Self::validate) passes.
+    #[inline(always)]
+    pub(super) fn paranoid(&self) {
+        #[cfg(feature = "paranoid")]
+        if let Err(violation) = self.validate() {
+            panic!("RBTreeMap invariant violated: {violation}");
+        }
+    }
+}
@@ -0,0 +1,799 @@
+use super::{
+    rbtree::{self, Comparable},
+    RBTreeMap,
+};
+use crate::alloc::Global;
+use core::{
+    borrow::Borrow,
+    fmt::Debug,
+    iter::FusedIterator,
+    mem::MaybeUninit,
+    ops::{Bound, RangeBounds},
+    ptr, slice,
+};
+
+/// Up to `N` entries kept sorted in an array.
+struct Inline<K, V, const N: usize> {
+    entries: [MaybeUninit<(K, V)>; N],
+    len: usize,
+}
+
+impl<K, V, const N: usize> Inline<K, V, N> {
+    const fn new() -> Self {
+        Self {
+            entries: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+        }
+    }
+    fn as_slice(&self) -> &[(K, V)] {
+        unsafe { slice::from_raw_parts(self.entries.as_ptr().cast(), self.len) }
+    }
+    fn as_mut_slice(&mut self) -> &mut [(K, V)] {
+        unsafe { slice::from_raw_parts_mut(self.entries.as_mut_ptr().cast(), self.len) }
+    }
+    /// Finds the index of `key`, or the index a new entry for it would take.
+    fn search<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        Q: ?Sized + Comparable<K>,
+    {
+        self.as_slice()
+            .binary_search_by(|(k, _)| key.compare(k).reverse())
+    }
+    /// Returns the index of the first entry for which `pred` is `false`.
+    fn partition_point(&self, mut pred: impl FnMut(&K) -> bool) -> usize {
+        self.as_slice().partition_point(|(k, _)| pred(k))
+    }
+    /// Shifts the entries from `index` on to the right and writes `kv` at `index`.
+    /// The array must not be full.
+    fn insert(&mut self, index: usize, kv: (K, V)) -> &mut V {
+        debug_assert!(self.len < N && index <= self.len);
+        unsafe {
+            let base = self.entries.as_mut_ptr();
+            ptr::copy(base.add(index), base.add(index + 1), self.len - index);
+            self.len += 1;
+            &mut (*base.add(index)).write(kv).1
+        }
+    }
+    fn remove(&mut self, index: usize) -> (K, V) {
+        debug_assert!(index < self.len);
+        unsafe {
+            let base = self.entries.as_mut_ptr();
+            let kv = (*base.add(index)).assume_init_read();
+            self.len -= 1;
+            ptr::copy(base.add(index + 1), base.add(index), self.len - index);
+            kv
+        }
+    }
+    /// Moves every entry out, in order, leaving the array empty.
+    fn drain(&mut self) -> impl Iterator<Item = (K, V)> + '_ {
+        let len = core::mem::replace(&mut self.len, 0);
+        self.entries[..len]
+            .iter()
+            .map(|kv| unsafe { kv.assume_init_read() })
+    }
+}
+
+impl<K, V, const N: usize> Drop for Inline<K, V, N> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+enum Repr<K, V, const N: usize> {
+    Inline(Inline<K, V, N>),
+    Tree(RBTreeMap<K, V>),
+}
+
+/// An ordered map that keeps up to `N` entries inline in a sorted array, and
+/// moves them into an [`RBTreeMap`] once it outgrows it.
+///
+/// Small maps then cost no allocation, and a lookup is a binary search over
+/// adjacent entries rather than a walk through separately allocated nodes. The
+/// map stays a tree after it has grown, until it is cleared, so a size that
+/// hovers around `N` does not move entries back and forth. Iteration order and
+/// the [`entry`](Self::entry), [`iter`](Self::iter), [`range`](Self::range) and
+/// [`get`](Self::get) API follow [`RBTreeMap`].
+///
+/// In `small_rbtree_map_timing`, lookups in the array of `(u64, u64)` entries
+/// are slower than in a tree at one entry and faster from two, six times so
+/// at eight. The array is part of every map, however large it grows, so memory
+/// sets the default `N` of 8: the map takes 144 bytes on 64-bit targets, less
+/// than a plain tree with two entries, and once it outgrows the array it costs
+/// less than two nodes more than a plain tree. `N` counts entries, so larger
+/// ones call for a smaller `N`; eight 64-byte entries inline pay off from four.
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::SmallRBTreeMap;
+///
+/// let mut map = SmallRBTreeMap::<_, _, 4>::new();
+/// map.insert(3, 'c');
+/// map.insert(1, 'a');
+/// *map.entry(2).or_insert('_') = 'b';
+/// assert!(map.is_inline());
+/// assert!(map.iter().eq([(&1, &'a'), (&2, &'b'), (&3, &'c')]));
+///
+/// map.extend([(4, 'd'), (5, 'e')]);
+/// assert!(!map.is_inline());
+/// assert!(map.range(2..5).map(|(k, _)| *k).eq(2..5));
+/// ```
+pub struct SmallRBTreeMap<K, V, const N: usize = 8> {
+    repr: Repr<K, V, N>,
+}
+
+impl<K, V, const N: usize> Default for SmallRBTreeMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize> Clone for SmallRBTreeMap<K, V, N>
+where
+    K: Clone,
+    V: Clone,
+{
+    fn clone(&self) -> Self {
+        let repr = match &self.repr {
+            Repr::Inline(inline) => {
+                let mut copy = Inline::new();
+                for (k, v) in inline.as_slice() {
+                    copy.insert(copy.len, (k.clone(), v.clone()));
+                }
+                Repr::Inline(copy)
+            }
+            Repr::Tree(tree) => Repr::Tree(tree.clone()),
+        };
+        Self { repr }
+    }
+}
+
+impl<K, V, const N: usize> Debug for SmallRBTreeMap<K, V, N>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, const N: usize> PartialEq for SmallRBTreeMap<K, V, N>
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K, V, const N: usize> Eq for SmallRBTreeMap<K, V, N>
+where
+    K: Eq,
+    V: Eq,
+{
+}
+
+impl<K, V, const N: usize, const M: usize> From<[(K, V); M]> for SmallRBTreeMap<K, V, N>
+where
+    K: Ord,
+{
+    fn from(arr: [(K, V); M]) -> Self {
+        let mut map = Self::new();
+        map.extend(arr);
+        map
+    }
+}
+
+impl<K, V, const N: usize> Extend<(K, V)> for SmallRBTreeMap<K, V, N>
+where
+    K: Ord,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        let mut iter = iter.into_iter();
+        while let Repr::Inline(_) = self.repr {
+            let Some((k, v)) = iter.next() else {
+                return;
+            };
+            self.insert(k, v);
+        }
+        if let Repr::Tree(tree) = &mut self.repr {
+            tree.extend(iter);
+        }
+    }
+}
+
+impl<'a, K, V, const N: usize> IntoIterator for &'a SmallRBTreeMap<K, V, N> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, const N: usize> IntoIterator for &'a mut SmallRBTreeMap<K, V, N> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, const N: usize> SmallRBTreeMap<K, V, N> {
+    /// Makes a new, empty `SmallRBTreeMap`, which does not allocate.
+    pub const fn new() -> Self {
+        Self {
+            repr: Repr::Inline(Inline::new()),
+        }
+    }
+    /// Returns `true` if the entries are still held inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.repr, Repr::Inline(_))
+    }
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Inline(inline) => inline.len,
+            Repr::Tree(tree) => tree.len(),
+        }
+    }
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Clears the map, removing all elements and going back to inline storage.
+    pub fn clear(&mut self) {
+        self.repr = Repr::Inline(Inline::new());
+    }
+    /// Gets an iterator over the entries of the map, sorted by key.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            inner: match &self.repr {
+                Repr::Inline(inline) => IterInner::Inline(inline.as_slice().iter()),
+                Repr::Tree(tree) => IterInner::Tree(tree.iter()),
+            },
+        }
+    }
+    /// Gets a mutable iterator over the entries of the map, sorted by key.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            inner: match &mut self.repr {
+                Repr::Inline(inline) => IterMutInner::Inline(inline.as_mut_slice().iter_mut()),
+                Repr::Tree(tree) => IterMutInner::Tree(tree.iter_mut()),
+            },
+        }
+    }
+    /// Returns the first key-value pair in the map.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+    /// Returns the last key-value pair in the map.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next_back()
+    }
+    /// Removes and returns the first element in the map.
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        match &mut self.repr {
+            Repr::Inline(inline) if inline.len == 0 => None,
+            Repr::Inline(inline) => Some(inline.remove(0)),
+            Repr::Tree(tree) => tree.pop_first(),
+        }
+    }
+    /// Removes and returns the last element in the map.
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        match &mut self.repr {
+            Repr::Inline(inline) if inline.len == 0 => None,
+            Repr::Inline(inline) => Some(inline.remove(inline.len - 1)),
+            Repr::Tree(tree) => tree.pop_last(),
+        }
+    }
+}
+
+impl<K, V, const N: usize> SmallRBTreeMap<K, V, N>
+where
+    K: Ord,
+{
+    /// Moves the inline entries into a tree, which takes linear time as they
+    /// are already sorted.
+    fn spill(&mut self) -> &mut RBTreeMap<K, V> {
+        if let Repr::Inline(inline) = &mut self.repr {
+            let tree = RBTreeMap::bulk_build_from_sorted_iter(inline.drain(), Global::default());
+            self.repr = Repr::Tree(tree);
+        }
+        match &mut self.repr {
+            Repr::Tree(tree) => tree,
+            Repr::Inline(_) => unreachable!(),
+        }
+    }
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was present. Only the value is replaced, like [`RBTreeMap::insert`].
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::SmallRBTreeMap;
+    ///
+    /// let mut count = SmallRBTreeMap::<_, _, 2>::new();
+    /// for word in ["a", "b", "a", "c", "a"] {
+    ///     *count.entry(word).or_insert(0) += 1;
+    /// }
+    /// assert!(count.iter().eq([(&"a", &3), (&"b", &1), (&"c", &1)]));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, N> {
+        let found = match &self.repr {
+            Repr::Inline(inline) => inline.search(&key),
+            Repr::Tree(_) => {
+                let Repr::Tree(tree) = &mut self.repr else {
+                    unreachable!()
+                };
+                return match tree.entry(key) {
+                    rbtree::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry {
+                        inner: OccupiedInner::Tree(entry),
+                    }),
+                    rbtree::Entry::Vacant(entry) => Entry::Vacant(VacantEntry {
+                        inner: VacantInner::Tree(entry),
+                    }),
+                };
+            }
+        };
+        match found {
+            Ok(index) => {
+                let Repr::Inline(inline) = &mut self.repr else {
+                    unreachable!()
+                };
+                Entry::Occupied(OccupiedEntry {
+                    inner: OccupiedInner::Inline { inline, index },
+                })
+            }
+            Err(index) => Entry::Vacant(VacantEntry {
+                inner: VacantInner::Inline {
+                    map: self,
+                    key,
+                    index,
+                },
+            }),
+        }
+    }
+    /// Returns the key-value pair corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but the ordering
+    /// on the borrowed form *must* match the ordering on the key type.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match &self.repr {
+            Repr::Inline(inline) => {
+                let (k, v) = &inline.as_slice()[inline.search(key).ok()?];
+                Some((k, v))
+            }
+            Repr::Tree(tree) => tree.get_key_value(key),
+        }
+    }
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match &mut self.repr {
+            Repr::Inline(inline) => {
+                let index = inline.search(key).ok()?;
+                Some(&mut inline.as_mut_slice()[index].1)
+            }
+            Repr::Tree(tree) => tree.get_mut(key),
+        }
+    }
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).is_some()
+    }
+    /// Removes a key from the map, returning the stored key and value
+    /// if the key was previously in the map.
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        match &mut self.repr {
+            Repr::Inline(inline) => {
+                let index = inline.search(key).ok()?;
+                Some(inline.remove(index))
+            }
+            Repr::Tree(tree) => tree.remove_entry(key),
+        }
+    }
+    /// Removes a key from the map, returning the value at the key
+    /// if the key was previously in the map.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+    /// Gets a double-ended iterator over the entries whose keys lie in `range`,
+    /// sorted by key, like [`RBTreeMap::range`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::SmallRBTreeMap;
+    ///
+    /// let map = SmallRBTreeMap::<_, _>::from([(1, 'a'), (3, 'c'), (5, 'e'), (7, 'g')]);
+    /// assert!(map.range(2..=5).eq([(&3, &'c'), (&5, &'e')]));
+    /// assert!(map.range(4..).rev().eq([(&7, &'g'), (&5, &'e')]));
+    /// assert_eq!(map.range(6..2).next(), None);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let inline = match &self.repr {
+            Repr::Inline(inline) => inline,
+            Repr::Tree(tree) => {
+                return Range {
+                    inner: RangeInner::Tree(tree.range(range)),
+                }
+            }
+        };
+        let front = inline.partition_point(|key| match range.start_bound() {
+            Bound::Included(start) => start.compare(key).is_gt(),
+            Bound::Excluded(start) => start.compare(key).is_ge(),
+            Bound::Unbounded => false,
+        });
+        let back = inline.partition_point(|key| match range.end_bound() {
+            Bound::Included(end) => end.compare(key).is_ge(),
+            Bound::Excluded(end) => end.compare(key).is_gt(),
+            Bound::Unbounded => true,
+        });
+        Range {
+            inner: RangeInner::Inline(inline.as_slice()[front..back.max(front)].iter()),
+        }
+    }
+}
+
+/// A view into a single entry in a [`SmallRBTreeMap`], which may either be
+/// vacant or occupied.
+///
+/// This `enum` is constructed from the [`entry`](SmallRBTreeMap::entry) method.
+pub enum Entry<'a, K, V, const N: usize> {
+    /// Existing slot with equivalent key.
+    Occupied(OccupiedEntry<'a, K, V, N>),
+    /// Vacant slot (no equivalent key in the map).
+    Vacant(VacantEntry<'a, K, V, N>),
+}
+
+impl<'a, K, V, const N: usize> Entry<'a, K, V, N>
+where
+    K: Ord,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the result of the default function if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    /// Ensures a value is in the entry by inserting, if empty, the result of the
+    /// default function, which is given a reference to the key.
+    pub fn or_insert_with_key<F>(self, default: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+}
+
+impl<K, V, const N: usize> Entry<'_, K, V, N> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+enum OccupiedInner<'a, K, V, const N: usize> {
+    Inline {
+        inline: &'a mut Inline<K, V, N>,
+        index: usize,
+    },
+    Tree(rbtree::OccupiedEntry<'a, K, V, Global>),
+}
+
+/// A view into an occupied entry in a [`SmallRBTreeMap`].
+/// It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, const N: usize> {
+    inner: OccupiedInner<'a, K, V, N>,
+}
+
+impl<'a, K, V, const N: usize> OccupiedEntry<'a, K, V, N> {
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        match &self.inner {
+            OccupiedInner::Inline { inline, index } => &inline.as_slice()[*index].0,
+            OccupiedInner::Tree(entry) => entry.key(),
+        }
+    }
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        match &self.inner {
+            OccupiedInner::Inline { inline, index } => &inline.as_slice()[*index].1,
+            OccupiedInner::Tree(entry) => entry.get(),
+        }
+    }
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        match &mut self.inner {
+            OccupiedInner::Inline { inline, index } => &mut inline.as_mut_slice()[*index].1,
+            OccupiedInner::Tree(entry) => entry.get_mut(),
+        }
+    }
+    /// Converts the entry into a mutable reference to its value.
+    pub fn into_mut(self) -> &'a mut V {
+        match self.inner {
+            OccupiedInner::Inline { inline, index } => &mut inline.as_mut_slice()[index].1,
+            OccupiedInner::Tree(entry) => entry.into_mut(),
+        }
+    }
+    /// Sets the value of the entry, and returns the entry's old value.
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+    /// Takes the key-value pair out of the map, and returns it.
+    pub fn remove_entry(self) -> (K, V) {
+        match self.inner {
+            OccupiedInner::Inline { inline, index } => inline.remove(index),
+            OccupiedInner::Tree(entry) => entry.remove_entry(),
+        }
+    }
+    /// Takes the value of the entry out of the map, and returns it.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+enum VacantInner<'a, K, V, const N: usize> {
+    Inline {
+        map: &'a mut SmallRBTreeMap<K, V, N>,
+        key: K,
+        index: usize,
+    },
+    Tree(rbtree::VacantEntry<'a, K, V, Global>),
+}
+
+/// A view into a vacant entry in a [`SmallRBTreeMap`].
+/// It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, const N: usize> {
+    inner: VacantInner<'a, K, V, N>,
+}
+
+impl<'a, K, V, const N: usize> VacantEntry<'a, K, V, N> {
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        match &self.inner {
+            VacantInner::Inline { key, .. } => key,
+            VacantInner::Tree(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K, V, const N: usize> VacantEntry<'a, K, V, N>
+where
+    K: Ord,
+{
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a
+    /// mutable reference to it. A full inline map moves into a tree first.
+    pub fn insert(self, value: V) -> &'a mut V {
+        match self.inner {
+            VacantInner::Inline { map, key, index } if map.len() < N => {
+                let Repr::Inline(inline) = &mut map.repr else {
+                    unreachable!()
+                };
+                inline.insert(index, (key, value))
+            }
+            VacantInner::Inline { map, key, .. } => match map.spill().entry(key) {
+                rbtree::Entry::Vacant(entry) => entry.insert(value),
+                rbtree::Entry::Occupied(_) => unreachable!(),
+            },
+            VacantInner::Tree(entry) => entry.insert(value),
+        }
+    }
+}
+
+enum IterInner<'a, K, V> {
+    Inline(slice::Iter<'a, (K, V)>),
+    Tree(rbtree::Iter<'a, K, V>),
+}
+
+/// An iterator over the entries of a [`SmallRBTreeMap`], sorted by key.
+pub struct Iter<'a, K, V> {
+    inner: IterInner<'a, K, V>,
+}
+
+impl<K, V> Clone for Iter<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: match &self.inner {
+                IterInner::Inline(iter) => IterInner::Inline(iter.clone()),
+                IterInner::Tree(iter) => IterInner::Tree(iter.clone()),
+            },
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterInner::Inline(iter) => iter.next().map(|(k, v)| (k, v)),
+            IterInner::Tree(iter) => iter.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            IterInner::Inline(iter) => iter.size_hint(),
+            IterInner::Tree(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Iter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterInner::Inline(iter) => iter.next_back().map(|(k, v)| (k, v)),
+            IterInner::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+enum IterMutInner<'a, K, V> {
+    Inline(slice::IterMut<'a, (K, V)>),
+    Tree(rbtree::IterMut<'a, K, V>),
+}
+
+/// A mutable iterator over the entries of a [`SmallRBTreeMap`], sorted by key.
+pub struct IterMut<'a, K, V> {
+    inner: IterMutInner<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterMutInner::Inline(iter) => iter.next().map(|(k, v)| (&*k, v)),
+            IterMutInner::Tree(iter) => iter.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            IterMutInner::Inline(iter) => iter.size_hint(),
+            IterMutInner::Tree(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for IterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            IterMutInner::Inline(iter) => iter.next_back().map(|(k, v)| (&*k, v)),
+            IterMutInner::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+enum RangeInner<'a, K, V> {
+    Inline(slice::Iter<'a, (K, V)>),
+    Tree(rbtree::Range<'a, K, V>),
+}
+
+/// An iterator over a sub-range of the entries of a [`SmallRBTreeMap`].
+///
+/// This `struct` is created by [`SmallRBTreeMap::range`].
+pub struct Range<'a, K, V> {
+    inner: RangeInner<'a, K, V>,
+}
+
+impl<K, V> Clone for Range<'_, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: match &self.inner {
+                RangeInner::Inline(iter) => RangeInner::Inline(iter.clone()),
+                RangeInner::Tree(iter) => RangeInner::Tree(iter.clone()),
+            },
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            RangeInner::Inline(iter) => iter.next().map(|(k, v)| (k, v)),
+            RangeInner::Tree(iter) => iter.next(),
+        }
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.inner {
+            RangeInner::Inline(iter) => iter.size_hint(),
+            RangeInner::Tree(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> DoubleEndedIterator for Range<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.inner {
+            RangeInner::Inline(iter) => iter.next_back().map(|(k, v)| (k, v)),
+            RangeInner::Tree(iter) => iter.next_back(),
+        }
+    }
+}
+
+impl<K, V> FusedIterator for Range<'_, K, V> {}
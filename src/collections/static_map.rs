@@ -0,0 +1,997 @@
+use super::rbtree_map::InvariantViolation;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt::Debug,
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Bound, Index, RangeBounds},
+};
+
+/// The index standing for a missing node.
+const NIL: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+struct Link {
+    /// The left and right children. A free slot keeps the next free slot in `child[1]`.
+    child: [usize; 2],
+    parent: usize,
+    red: bool,
+}
+
+impl Link {
+    const EMPTY: Link = Link {
+        child: [NIL; 2],
+        parent: NIL,
+        red: false,
+    };
+}
+
+/// Returns the node furthest towards `side` in the subtree of `node`.
+fn extreme(links: &[Link], mut node: usize, side: usize) -> usize {
+    if node == NIL {
+        return NIL;
+    }
+    while links[node].child[side] != NIL {
+        node = links[node].child[side];
+    }
+    node
+}
+
+/// Returns the neighbour of `node` towards `side`, or `NIL`.
+///
+/// Only the links are read, so iterators can step while handing out the entries.
+fn step(links: &[Link], mut node: usize, side: usize) -> usize {
+    if links[node].child[side] != NIL {
+        return extreme(links, links[node].child[side], 1 - side);
+    }
+    loop {
+        let parent = links[node].parent;
+        if parent == NIL || links[parent].child[1 - side] == node {
+            return parent;
+        }
+        node = parent;
+    }
+}
+
+/// A red-black tree map storing up to `N` entries inline, without an allocator.
+///
+/// Nodes live in an array inside the map and are linked by index. Slots are
+/// handed out in order and recycled through an intrusive free list, so the map
+/// never allocates and can be placed in a `static`. The API follows
+/// [`RBTreeMap`](super::RBTreeMap), except that [`insert`] and inserting through
+/// an [`Entry`] fail when the map is full, and [`Extend`] panics.
+///
+/// [`insert`]: StaticRBTreeMap::insert
+///
+/// # Examples
+///
+/// ```
+/// use xsl::collections::StaticRBTreeMap;
+///
+/// let mut map = StaticRBTreeMap::<u32, &str, 2>::new();
+/// assert_eq!(map.insert(2, "b"), Ok(None));
+/// assert_eq!(map.insert(1, "a"), Ok(None));
+/// assert_eq!(map.insert(3, "c"), Err((3, "c")));
+/// // replacing a value needs no new slot
+/// assert_eq!(map.insert(2, "x"), Ok(Some("b")));
+/// assert!(map.iter().eq([(&1, &"a"), (&2, &"x")]));
+/// ```
+///
+/// In a `static`:
+///
+/// ```
+/// use core::cell::RefCell;
+/// use xsl::collections::StaticRBTreeMap;
+///
+/// struct Registry(RefCell<StaticRBTreeMap<u8, u16, 16>>);
+/// unsafe impl Sync for Registry {}
+///
+/// static REGISTRY: Registry = Registry(RefCell::new(StaticRBTreeMap::new()));
+///
+/// REGISTRY.0.borrow_mut().insert(7, 700).unwrap();
+/// assert_eq!(REGISTRY.0.borrow().get(&7), Some(&700));
+/// ```
+pub struct StaticRBTreeMap<K, V, const N: usize> {
+    entries: [MaybeUninit<(K, V)>; N],
+    links: [Link; N],
+    root: usize,
+    /// The head of the free list.
+    free: usize,
+    /// Slots from here on have never been used.
+    bump: usize,
+    length: usize,
+}
+
+impl<K, V, const N: usize> Default for StaticRBTreeMap<K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize> Drop for StaticRBTreeMap<K, V, N> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, V, const N: usize> Debug for StaticRBTreeMap<K, V, N>
+where
+    K: Debug,
+    V: Debug,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'a, K, V, const N: usize> IntoIterator for &'a StaticRBTreeMap<K, V, N> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V, N>;
+    fn into_iter(self) -> Iter<'a, K, V, N> {
+        self.iter()
+    }
+}
+
+impl<K, V, Q, const N: usize> Index<&Q> for StaticRBTreeMap<K, V, N>
+where
+    K: Ord + Borrow<Q>,
+    Q: ?Sized + Ord,
+{
+    type Output = V;
+    /// Returns a reference to the value corresponding to the supplied key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, index: &Q) -> &V {
+        self.get(index).expect("no entry found for key")
+    }
+}
+
+impl<K, V, const N: usize> Extend<(K, V)> for StaticRBTreeMap<K, V, N>
+where
+    K: Ord,
+{
+    /// Extends the map with the pairs of an iterator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a new key does not fit; use [`insert`](StaticRBTreeMap::insert)
+    /// to handle a full map.
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            if self.insert(k, v).is_err() {
+                panic!("StaticRBTreeMap is full");
+            }
+        }
+    }
+}
+
+impl<'a, K, V, const N: usize> Extend<(&'a K, &'a V)> for StaticRBTreeMap<K, V, N>
+where
+    K: Ord + Copy,
+    V: Copy,
+{
+    fn extend<I: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().map(|(&key, &value)| (key, value)));
+    }
+}
+
+impl<K, V, const N: usize> StaticRBTreeMap<K, V, N> {
+    /// Makes a new, empty `StaticRBTreeMap`.
+    pub const fn new() -> Self {
+        Self {
+            entries: [const { MaybeUninit::uninit() }; N],
+            links: [Link::EMPTY; N],
+            root: NIL,
+            free: NIL,
+            bump: 0,
+            length: 0,
+        }
+    }
+    /// Returns the number of elements in the map.
+    pub const fn len(&self) -> usize {
+        self.length
+    }
+    /// Returns `true` if the map contains no elements.
+    pub const fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+    /// Returns the number of elements the map can hold.
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// Returns `true` if inserting a new key would fail.
+    pub const fn is_full(&self) -> bool {
+        self.length == N
+    }
+    /// Clears the map, removing all elements.
+    pub fn clear(&mut self) {
+        let mut node = self.first();
+        while node != NIL {
+            let next = self.successor(node);
+            unsafe { self.entries[node].assume_init_drop() };
+            node = next;
+        }
+        self.root = NIL;
+        self.free = NIL;
+        self.bump = 0;
+        self.length = 0;
+    }
+    /// Gets an iterator over the entries of the map, sorted by key.
+    pub fn iter(&self) -> Iter<'_, K, V, N> {
+        Iter {
+            map: self,
+            front: self.first(),
+            back: self.last(),
+            length: self.length,
+        }
+    }
+    /// Gets a mutable iterator over the entries of the map, sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::StaticRBTreeMap;
+    ///
+    /// let mut map = StaticRBTreeMap::<_, _, 8>::new();
+    /// for i in 0..8 {
+    ///     map.insert(i, i).unwrap();
+    /// }
+    /// map.iter_mut().for_each(|(_, v)| *v *= 10);
+    /// assert_eq!(map.get(&7), Some(&70));
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, N> {
+        IterMut {
+            front: self.first(),
+            back: self.last(),
+            length: self.length,
+            entries: self.entries.as_mut_ptr(),
+            links: &self.links,
+            _marker: PhantomData,
+        }
+    }
+    /// Returns the first key-value pair in the map.
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.key_value(self.first())
+    }
+    /// Returns the last key-value pair in the map.
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.key_value(self.last())
+    }
+    /// Removes and returns the first element in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::StaticRBTreeMap;
+    ///
+    /// let mut map = StaticRBTreeMap::<_, _, 4>::new();
+    /// map.insert(2, 'b').unwrap();
+    /// map.insert(1, 'a').unwrap();
+    /// assert_eq!(map.pop_first(), Some((1, 'a')));
+    /// assert_eq!(map.pop_first(), Some((2, 'b')));
+    /// assert_eq!(map.pop_first(), None);
+    /// ```
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        match self.first() {
+            NIL => None,
+            node => Some(self.remove_node(node)),
+        }
+    }
+    /// Removes and returns the last element in the map.
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        match self.last() {
+            NIL => None,
+            node => Some(self.remove_node(node)),
+        }
+    }
+
+    fn key_value(&self, node: usize) -> Option<(&K, &V)> {
+        if node == NIL {
+            return None;
+        }
+        let (k, v) = unsafe { self.entries[node].assume_init_ref() };
+        Some((k, v))
+    }
+    fn key(&self, node: usize) -> &K {
+        unsafe { &self.entries[node].assume_init_ref().0 }
+    }
+    fn is_red(&self, node: usize) -> bool {
+        node != NIL && self.links[node].red
+    }
+    fn set_black(&mut self, node: usize) {
+        if node != NIL {
+            self.links[node].red = false;
+        }
+    }
+    /// Returns which child of its parent `node` is.
+    fn side(&self, node: usize) -> usize {
+        (self.links[self.links[node].parent].child[1] == node) as usize
+    }
+    fn extreme(&self, node: usize, side: usize) -> usize {
+        extreme(&self.links, node, side)
+    }
+    fn first(&self) -> usize {
+        self.extreme(self.root, 0)
+    }
+    fn last(&self) -> usize {
+        self.extreme(self.root, 1)
+    }
+    fn step(&self, node: usize, side: usize) -> usize {
+        step(&self.links, node, side)
+    }
+    fn successor(&self, node: usize) -> usize {
+        self.step(node, 1)
+    }
+    /// Replaces `old` by `new` in the link from `old`'s parent.
+    fn transplant(&mut self, old: usize, new: usize) {
+        let parent = self.links[old].parent;
+        if parent == NIL {
+            self.root = new;
+        } else {
+            let side = self.side(old);
+            self.links[parent].child[side] = new;
+        }
+        if new != NIL {
+            self.links[new].parent = parent;
+        }
+    }
+    /// Rotates `node` down towards `side`, lifting its other child.
+    fn rotate(&mut self, node: usize, side: usize) {
+        let up = self.links[node].child[1 - side];
+        let inner = self.links[up].child[side];
+        self.links[node].child[1 - side] = inner;
+        if inner != NIL {
+            self.links[inner].parent = node;
+        }
+        self.transplant(node, up);
+        self.links[up].child[side] = node;
+        self.links[node].parent = up;
+    }
+    fn insert_fixup(&mut self, mut node: usize) {
+        while self.is_red(self.links[node].parent) {
+            let parent = self.links[node].parent;
+            let grandparent = self.links[parent].parent;
+            let side = self.side(parent);
+            let uncle = self.links[grandparent].child[1 - side];
+            if self.is_red(uncle) {
+                self.links[parent].red = false;
+                self.links[uncle].red = false;
+                self.links[grandparent].red = true;
+                node = grandparent;
+                continue;
+            }
+            let parent = if self.side(node) != side {
+                self.rotate(parent, side);
+                node = parent;
+                self.links[node].parent
+            } else {
+                parent
+            };
+            self.links[parent].red = false;
+            self.links[grandparent].red = true;
+            self.rotate(grandparent, 1 - side);
+        }
+        self.set_black(self.root);
+    }
+    fn remove_node(&mut self, node: usize) -> (K, V) {
+        let [left, right] = self.links[node].child;
+        let mut removed_red = self.links[node].red;
+        let (child, parent);
+        if left == NIL || right == NIL {
+            child = if left == NIL { right } else { left };
+            parent = self.links[node].parent;
+            self.transplant(node, child);
+        } else {
+            let next = self.extreme(right, 0);
+            removed_red = self.links[next].red;
+            child = self.links[next].child[1];
+            if self.links[next].parent == node {
+                parent = next;
+            } else {
+                parent = self.links[next].parent;
+                self.transplant(next, child);
+                self.links[next].child[1] = right;
+                self.links[right].parent = next;
+            }
+            self.transplant(node, next);
+            self.links[next].child[0] = left;
+            self.links[left].parent = next;
+            self.links[next].red = self.links[node].red;
+        }
+        if !removed_red {
+            self.remove_fixup(child, parent);
+        }
+        self.links[node] = Link::EMPTY;
+        self.links[node].child[1] = self.free;
+        self.free = node;
+        self.length -= 1;
+        unsafe { self.entries[node].assume_init_read() }
+    }
+    fn remove_fixup(&mut self, mut node: usize, mut parent: usize) {
+        while node != self.root && !self.is_red(node) {
+            let side = (self.links[parent].child[1] == node) as usize;
+            let mut sibling = self.links[parent].child[1 - side];
+            if self.is_red(sibling) {
+                self.links[sibling].red = false;
+                self.links[parent].red = true;
+                self.rotate(parent, side);
+                sibling = self.links[parent].child[1 - side];
+            }
+            let [near, far] = {
+                let child = self.links[sibling].child;
+                [child[side], child[1 - side]]
+            };
+            if !self.is_red(near) && !self.is_red(far) {
+                self.links[sibling].red = true;
+                node = parent;
+                parent = self.links[node].parent;
+                continue;
+            }
+            if !self.is_red(far) {
+                self.links[near].red = false;
+                self.links[sibling].red = true;
+                self.rotate(sibling, 1 - side);
+                sibling = self.links[parent].child[1 - side];
+            }
+            self.links[sibling].red = self.links[parent].red;
+            self.links[parent].red = false;
+            let far = self.links[sibling].child[1 - side];
+            self.set_black(far);
+            self.rotate(parent, side);
+            node = self.root;
+        }
+        self.set_black(node);
+    }
+}
+
+impl<K, V, const N: usize> StaticRBTreeMap<K, V, N>
+where
+    K: Ord,
+{
+    /// Finds the node holding `key`, or the parent and side a new node would take.
+    fn search<Q>(&self, key: &Q) -> Result<usize, (usize, usize)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let mut parent = NIL;
+        let mut side = 0;
+        let mut node = self.root;
+        while node != NIL {
+            side = match key.cmp(self.key(node).borrow()) {
+                Ordering::Equal => return Ok(node),
+                Ordering::Less => 0,
+                Ordering::Greater => 1,
+            };
+            parent = node;
+            node = self.links[node].child[side];
+        }
+        Err((parent, side))
+    }
+    /// Inserts a key-value pair into the map.
+    ///
+    /// Returns the old value if the key was present, replacing only the value.
+    /// A new key that does not fit is handed back as `Err((key, value))`.
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, (K, V)> {
+        match self.search(&key) {
+            Ok(node) => {
+                let slot = unsafe { &mut self.entries[node].assume_init_mut().1 };
+                Ok(Some(core::mem::replace(slot, value)))
+            }
+            Err((parent, side)) => self.insert_at(parent, side, key, value).map(|_| None),
+        }
+    }
+    /// Links a new node under `parent` towards `side`, or hands the pair back
+    /// if no slot is left.
+    fn insert_at(&mut self, parent: usize, side: usize, key: K, value: V) -> Result<usize, (K, V)> {
+        let node = if self.free != NIL {
+            let node = self.free;
+            self.free = self.links[node].child[1];
+            node
+        } else if self.bump < N {
+            self.bump += 1;
+            self.bump - 1
+        } else {
+            return Err((key, value));
+        };
+        self.entries[node].write((key, value));
+        self.links[node] = Link {
+            child: [NIL; 2],
+            parent,
+            red: true,
+        };
+        if parent == NIL {
+            self.root = node;
+        } else {
+            self.links[parent].child[side] = node;
+        }
+        self.length += 1;
+        self.insert_fixup(node);
+        Ok(node)
+    }
+    /// Gets the given key's corresponding entry in the map for in-place manipulation.
+    ///
+    /// Inserting through a [`VacantEntry`] fails like [`insert`](Self::insert)
+    /// when the map is full.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::StaticRBTreeMap;
+    ///
+    /// let mut count = StaticRBTreeMap::<_, _, 2>::new();
+    /// for word in ["a", "b", "a"] {
+    ///     *count.entry(word).or_insert(0).unwrap() += 1;
+    /// }
+    /// assert!(count.iter().eq([(&"a", &2), (&"b", &1)]));
+    /// assert_eq!(count.entry("c").or_insert(1), Err(("c", 1)));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, N> {
+        match self.search(&key) {
+            Ok(node) => Entry::Occupied(OccupiedEntry { map: self, node }),
+            Err((parent, side)) => Entry::Vacant(VacantEntry {
+                map: self,
+                key,
+                parent,
+                side,
+            }),
+        }
+    }
+    /// Returns the last node whose key satisfies `pred` and the first one that
+    /// does not, given that `pred` holds for a prefix of the keys.
+    fn partition_point(&self, mut pred: impl FnMut(&K) -> bool) -> [usize; 2] {
+        let mut bounds = [NIL; 2];
+        let mut node = self.root;
+        while node != NIL {
+            let side = !pred(self.key(node)) as usize;
+            bounds[side] = node;
+            node = self.links[node].child[1 - side];
+        }
+        bounds
+    }
+    /// Gets a double-ended iterator over the entries whose keys lie in `range`,
+    /// sorted by key, like [`RBTreeMap::range`](super::RBTreeMap::range).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::StaticRBTreeMap;
+    ///
+    /// let mut map = StaticRBTreeMap::<_, _, 4>::new();
+    /// map.extend([(1, 'a'), (3, 'c'), (5, 'e'), (7, 'g')]);
+    /// assert!(map.range(2..=5).eq([(&3, &'c'), (&5, &'e')]));
+    /// assert!(map.range(4..).rev().eq([(&7, &'g'), (&5, &'e')]));
+    /// assert_eq!(map.range(6..2).next(), None);
+    /// ```
+    pub fn range<Q, R>(&self, range: R) -> Range<'_, K, V, N>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+        R: RangeBounds<Q>,
+    {
+        let [_, front] = self.partition_point(|key| match range.start_bound() {
+            Bound::Included(start) => start > key.borrow(),
+            Bound::Excluded(start) => start >= key.borrow(),
+            Bound::Unbounded => false,
+        });
+        let before_end = |key: &K| match range.end_bound() {
+            Bound::Included(end) => end >= key.borrow(),
+            Bound::Excluded(end) => end > key.borrow(),
+            Bound::Unbounded => true,
+        };
+        let [back, _] = self.partition_point(before_end);
+        let ends =
+            (front != NIL && back != NIL && before_end(self.key(front))).then_some([front, back]);
+        Range { map: self, ends }
+    }
+    /// Returns the key-value pair corresponding to the key.
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.key_value(self.search(key).ok()?)
+    }
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.get_key_value(key).map(|(_, v)| v)
+    }
+    /// Returns a mutable reference to the value corresponding to the key.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let node = self.search(key).ok()?;
+        Some(unsafe { &mut self.entries[node].assume_init_mut().1 })
+    }
+    /// Returns `true` if the map contains a value for the specified key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.search(key).is_ok()
+    }
+    /// Removes a key from the map, returning the stored key and value
+    /// if the key was previously in the map. Its slot is reused by later inserts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xsl::collections::StaticRBTreeMap;
+    ///
+    /// let mut map = StaticRBTreeMap::<_, _, 1>::new();
+    /// map.insert(1, "a").unwrap();
+    /// assert!(map.is_full());
+    /// assert_eq!(map.remove_entry(&1), Some((1, "a")));
+    /// assert_eq!(map.insert(2, "b"), Ok(None));
+    /// ```
+    pub fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        let node = self.search(key).ok()?;
+        Some(self.remove_node(node))
+    }
+    /// Removes a key from the map, returning the value at the key
+    /// if the key was previously in the map.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: ?Sized + Ord,
+    {
+        self.remove_entry(key).map(|(_, v)| v)
+    }
+    /// Checks that the map is a valid red-black tree, like
+    /// [`RBTreeMap::validate`](super::RBTreeMap::validate).
+    pub fn validate(&self) -> Result<(), InvariantViolation<'_, K>> {
+        if self.is_red(self.root) {
+            return Err(InvariantViolation::RedRoot {
+                key: self.key(self.root),
+            });
+        }
+        let mut prev: Option<&K> = None;
+        let mut count = 0;
+        self.validate_subtree(self.root, NIL, &mut prev, &mut count)?;
+        if count != self.length {
+            return Err(InvariantViolation::Length {
+                expected: self.length,
+                found: count,
+            });
+        }
+        Ok(())
+    }
+    fn validate_subtree<'a>(
+        &'a self,
+        node: usize,
+        parent: usize,
+        prev: &mut Option<&'a K>,
+        count: &mut usize,
+    ) -> Result<usize, InvariantViolation<'a, K>> {
+        if node == NIL {
+            return Ok(0);
+        }
+        let key = self.key(node);
+        if self.links[node].parent != parent {
+            return Err(InvariantViolation::ParentPointer { key });
+        }
+        if self.is_red(node) && self.is_red(parent) {
+            return Err(InvariantViolation::RedRed { key });
+        }
+        let [left, right] = self.links[node].child;
+        let left = self.validate_subtree(left, node, prev, count)?;
+        if prev.is_some_and(|prev| prev >= key) {
+            return Err(InvariantViolation::Order { key });
+        }
+        *prev = Some(key);
+        *count += 1;
+        let right = self.validate_subtree(right, node, prev, count)?;
+        if left != right {
+            return Err(InvariantViolation::BlackHeight { key });
+        }
+        Ok(left + !self.is_red(node) as usize)
+    }
+}
+
+/// An iterator over the entries of a [`StaticRBTreeMap`], sorted by key.
+pub struct Iter<'a, K, V, const N: usize> {
+    map: &'a StaticRBTreeMap<K, V, N>,
+    front: usize,
+    back: usize,
+    length: usize,
+}
+
+impl<K, V, const N: usize> Clone for Iter<'_, K, V, N> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            front: self.front,
+            back: self.back,
+            length: self.length,
+        }
+    }
+}
+
+impl<'a, K, V, const N: usize> Iterator for Iter<'a, K, V, N> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        let node = self.front;
+        if self.length != 0 {
+            self.front = self.map.successor(node);
+        }
+        self.map.key_value(node)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V, const N: usize> DoubleEndedIterator for Iter<'_, K, V, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        let node = self.back;
+        if self.length != 0 {
+            self.back = self.map.step(node, 0);
+        }
+        self.map.key_value(node)
+    }
+}
+
+impl<K, V, const N: usize> ExactSizeIterator for Iter<'_, K, V, N> {}
+
+impl<K, V, const N: usize> FusedIterator for Iter<'_, K, V, N> {}
+
+/// A mutable iterator over the entries of a [`StaticRBTreeMap`], sorted by key.
+pub struct IterMut<'a, K, V, const N: usize> {
+    /// The entry array, only ever reached through this pointer so that the
+    /// references already handed out stay valid.
+    entries: *mut MaybeUninit<(K, V)>,
+    links: &'a [Link; N],
+    front: usize,
+    back: usize,
+    length: usize,
+    _marker: PhantomData<&'a mut (K, V)>,
+}
+
+impl<'a, K, V, const N: usize> IterMut<'a, K, V, N> {
+    fn yield_mut(&self, node: usize) -> (&'a K, &'a mut V) {
+        let (k, v) = unsafe { (*self.entries.add(node)).assume_init_mut() };
+        (k, v)
+    }
+}
+
+impl<'a, K, V, const N: usize> Iterator for IterMut<'a, K, V, N> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        let node = self.front;
+        if self.length != 0 {
+            self.front = step(self.links, node, 1);
+        }
+        Some(self.yield_mut(node))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.length, Some(self.length))
+    }
+}
+
+impl<K, V, const N: usize> DoubleEndedIterator for IterMut<'_, K, V, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.length == 0 {
+            return None;
+        }
+        self.length -= 1;
+        let node = self.back;
+        if self.length != 0 {
+            self.back = step(self.links, node, 0);
+        }
+        Some(self.yield_mut(node))
+    }
+}
+
+impl<K, V, const N: usize> ExactSizeIterator for IterMut<'_, K, V, N> {}
+
+impl<K, V, const N: usize> FusedIterator for IterMut<'_, K, V, N> {}
+
+/// An iterator over a sub-range of the entries of a [`StaticRBTreeMap`], sorted by key.
+///
+/// This `struct` is created by the [`range`](StaticRBTreeMap::range) method.
+pub struct Range<'a, K, V, const N: usize> {
+    map: &'a StaticRBTreeMap<K, V, N>,
+    /// The next nodes to yield from the front and the back, or `None` once they have met.
+    ends: Option<[usize; 2]>,
+}
+
+impl<K, V, const N: usize> Clone for Range<'_, K, V, N> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            ends: self.ends,
+        }
+    }
+}
+
+impl<'a, K, V, const N: usize> Iterator for Range<'a, K, V, N> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let [front, back] = self.ends?;
+        self.ends = (front != back).then(|| [self.map.successor(front), back]);
+        self.map.key_value(front)
+    }
+}
+
+impl<K, V, const N: usize> DoubleEndedIterator for Range<'_, K, V, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let [front, back] = self.ends?;
+        self.ends = (front != back).then(|| [front, self.map.step(back, 0)]);
+        self.map.key_value(back)
+    }
+}
+
+impl<K, V, const N: usize> FusedIterator for Range<'_, K, V, N> {}
+
+/// A view into a single entry in a [`StaticRBTreeMap`], which may either be
+/// vacant or occupied.
+///
+/// This `enum` is constructed from the [`entry`](StaticRBTreeMap::entry) method.
+pub enum Entry<'a, K, V, const N: usize> {
+    /// Existing slot with equivalent key.
+    Occupied(OccupiedEntry<'a, K, V, N>),
+    /// Vacant slot (no equivalent key in the map).
+    Vacant(VacantEntry<'a, K, V, N>),
+}
+
+impl<'a, K, V, const N: usize> Entry<'a, K, V, N>
+where
+    K: Ord,
+{
+    /// Ensures a value is in the entry by inserting the default if empty, and returns
+    /// a mutable reference to the value in the entry.
+    ///
+    /// A vacant entry in a full map hands the pair back as `Err((key, default))`.
+    pub fn or_insert(self, default: V) -> Result<&'a mut V, (K, V)> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+    /// Ensures a value is in the entry by inserting the result of the default function if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<&'a mut V, (K, V)> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+    /// Ensures a value is in the entry by inserting, if empty, the result of the
+    /// default function, which is given a reference to the key.
+    pub fn or_insert_with_key<F>(self, default: F) -> Result<&'a mut V, (K, V)>
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+}
+
+impl<K, V, const N: usize> Entry<'_, K, V, N> {
+    /// Returns a reference to this entry's key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+/// A view into an occupied entry in a [`StaticRBTreeMap`].
+/// It is part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, const N: usize> {
+    map: &'a mut StaticRBTreeMap<K, V, N>,
+    node: usize,
+}
+
+impl<'a, K, V, const N: usize> OccupiedEntry<'a, K, V, N> {
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        self.map.key(self.node)
+    }
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        unsafe { &self.map.entries[self.node].assume_init_ref().1 }
+    }
+    /// Gets a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut self.map.entries[self.node].assume_init_mut().1 }
+    }
+    /// Converts the entry into a mutable reference to its value.
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut self.map.entries[self.node].assume_init_mut().1 }
+    }
+    /// Sets the value of the entry, and returns the entry's old value.
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+    /// Takes the key-value pair out of the map, and returns it.
+    pub fn remove_entry(self) -> (K, V) {
+        self.map.remove_node(self.node)
+    }
+    /// Takes the value of the entry out of the map, and returns it.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A view into a vacant entry in a [`StaticRBTreeMap`].
+/// It is part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, const N: usize> {
+    map: &'a mut StaticRBTreeMap<K, V, N>,
+    key: K,
+    parent: usize,
+    side: usize,
+}
+
+impl<'a, K, V, const N: usize> VacantEntry<'a, K, V, N> {
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the `VacantEntry`.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+}
+
+impl<'a, K, V, const N: usize> VacantEntry<'a, K, V, N>
+where
+    K: Ord,
+{
+    /// Sets the value of the entry with the `VacantEntry`'s key, and returns a
+    /// mutable reference to it, or hands the pair back if the map is full.
+    pub fn insert(self, value: V) -> Result<&'a mut V, (K, V)> {
+        let node = self
+            .map
+            .insert_at(self.parent, self.side, self.key, value)?;
+        Ok(unsafe { &mut self.map.entries[node].assume_init_mut().1 })
+    }
+}
@@ -47,7 +47,7 @@ impl<T> Default for Ptr<T> {
 
 impl<T> Clone for Ptr<T> {
     fn clone(&self) -> Self {
-        Self { ptr: self.ptr }
+        *self
     }
 }
 
@@ -94,7 +94,7 @@ impl<T> core::cmp::Eq for Ptr<T> {}
 
 impl<T> core::cmp::PartialOrd for Ptr<T> {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-        self.ptr.partial_cmp(&other.ptr)
+        Some(self.cmp(other))
     }
 }
 
@@ -1,3 +1,4 @@
+#![allow(clippy::clone_on_copy)]
 mod common;
 #[test]
 fn rbtree() {
@@ -14,6 +15,9 @@ fn rbtree() {
     common::repeat(test, 10);
 }
 
+// `paranoid` validates the whole map after every change, which makes the
+// tests timing a million entries run for hours
+#[cfg(not(feature = "paranoid"))]
 #[test]
 fn rbtree_timing() {
     let mut tree = std::collections::BTreeMap::new();
@@ -48,6 +52,10 @@ fn rbtree_timing() {
     };
     let duration = common::timing(test_insert);
     println!("rbtree insert: {:?}", duration);
+    // Every probe walks up to `height` nodes, against roughly log_B(n) nodes for BTreeMap.
+    let stats = tree.stats();
+    println!("rbtree after insert: {:?}", stats);
+    assert!(stats.height <= 2 * stats.black_height);
     let test_find = || {
         for k in &data {
             tree.get(k);
@@ -62,6 +70,693 @@ fn rbtree_timing() {
     };
     let duration = common::timing(test_remove);
     println!("rbtree remove: {:?}", duration);
+    println!("rbtree after remove: {:?}", tree.stats());
+}
+
+#[cfg(feature = "stats")]
+#[test]
+fn rbtree_stats_counters() {
+    use xsl::collections::RBTreeMap;
+    let stats = |keys: &[i32]| {
+        let mut map = RBTreeMap::new();
+        map.extend(keys.iter().map(|&k| (k, ())));
+        let stats = map.stats();
+        (stats.rotations, stats.recolorings)
+    };
+    assert_eq!(stats(&[]), (0, 0));
+    assert_eq!(stats(&[1, 2]), (0, 0));
+    // 3 hangs in line under a red parent with no uncle: one single rotation
+    assert_eq!(stats(&[1, 2, 3]), (1, 0));
+    // 2 hangs in a zig-zag under a red parent: a double rotation counts twice
+    assert_eq!(stats(&[3, 1, 2]), (2, 0));
+    // 4 has a red uncle, so recolouring alone restores the invariants
+    assert_eq!(stats(&[1, 2, 3, 4]), (1, 1));
+    // ascending keys alternate between rotating (3, 5, 7) and recolouring (4, 6)
+    assert_eq!(stats(&[1, 2, 3, 4, 5, 6, 7]), (3, 2));
+    // the counters keep the work done since creation, even once it is undone
+    let mut map = RBTreeMap::new();
+    map.extend((1..=7).map(|k| (k, ())));
+    map.clear();
+    map.insert(1, ());
+    assert_eq!(map.stats().rotations, 3);
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn rbtree_clear_timing() {
+    let data = common::rand_data(1000000, 0..1000000);
+    let tree: std::collections::BTreeMap<_, _> = data.iter().map(|&k| (k, 0)).collect();
+    let mut copy = None;
+    println!(
+        "btree clone: {:?}",
+        common::timing(|| copy = Some(tree.clone()))
+    );
+    println!("btree drop: {:?}", common::timing(|| drop(copy)));
+    let mut map = xsl::collections::RBTreeMap::new();
+    map.extend(data.iter().map(|&k| (k, 0)));
+    let mut copy = None;
+    println!(
+        "rbtree clone: {:?}",
+        common::timing(|| copy = Some(map.clone()))
+    );
+    let mut copy = copy.unwrap();
+    assert_eq!(copy.validate(), Ok(()));
+    assert_eq!(copy, map);
+    println!("rbtree clear: {:?}", common::timing(|| copy.clear()));
+    assert!(copy.is_empty());
+    println!("rbtree drop: {:?}", common::timing(|| drop(map)));
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn rbtree_pop_timing() {
+    let data = common::rand_data(1000000, 0..1000000);
+    let mut tree: std::collections::BTreeMap<_, _> = data.iter().map(|&k| (k, 0)).collect();
+    let test_first = || {
+        for _ in &data {
+            tree.first_key_value();
+        }
+    };
+    println!("btree first: {:?}", common::timing(test_first));
+    let test_iter = || {
+        for _ in &data {
+            tree.iter().next();
+        }
+    };
+    println!("btree iter start: {:?}", common::timing(test_iter));
+    let test_pop = || while tree.pop_first().is_some() {};
+    println!("btree pop_first: {:?}", common::timing(test_pop));
+    let mut tree = xsl::collections::RBTreeMap::new();
+    tree.extend(data.iter().map(|&k| (k, 0)));
+    let test_first = || {
+        for _ in &data {
+            tree.first_key_value();
+        }
+    };
+    println!("rbtree first: {:?}", common::timing(test_first));
+    let test_iter = || {
+        for _ in &data {
+            tree.iter().next();
+        }
+    };
+    println!("rbtree iter start: {:?}", common::timing(test_iter));
+    let test_pop = || while tree.pop_first().is_some() {};
+    println!("rbtree pop_first: {:?}", common::timing(test_pop));
+}
+
+#[test]
+fn rbtree_ends() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    let mut map = RBTreeMap::new();
+    let mut std_map = BTreeMap::new();
+    for _ in 0..5000 {
+        let k = rng.gen_range(0..200);
+        match rng.gen_range(0..5) {
+            0 | 1 => assert_eq!(map.insert(k, k), std_map.insert(k, k)),
+            2 => assert_eq!(map.remove(&k), std_map.remove(&k)),
+            3 => assert_eq!(map.pop_first(), std_map.pop_first()),
+            _ => assert_eq!(map.pop_last(), std_map.pop_last()),
+        }
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.first_key_value(), std_map.first_key_value());
+        assert_eq!(map.last_key_value(), std_map.last_key_value());
+        assert_eq!(map.iter().next_back(), std_map.iter().next_back());
+    }
+    map.clear();
+    assert_eq!(map.first_key_value(), None);
+    map.insert(1, 1);
+    assert_eq!(map.clone().pop_last(), Some((1, 1)));
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn rbtree_nearly_sorted() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    // ascending timestamps with one straggler in a hundred
+    let data: Vec<_> = (0..1000000)
+        .map(|i| match rng.gen_range(0..100) {
+            0 => rng.gen_range(0..1000000),
+            _ => i,
+        })
+        .collect();
+    let mut tree = BTreeMap::new();
+    let test_extend = || tree.extend(data.iter().map(|&k| (k, k)));
+    println!("btree extend: {:?}", common::timing(test_extend));
+    let mut map = RBTreeMap::new();
+    let test_insert = || {
+        for &k in &data {
+            map.insert(k, k);
+        }
+    };
+    println!("rbtree insert: {:?}", common::timing(test_insert));
+    let mut extended = RBTreeMap::new();
+    let test_extend = || extended.extend(data.iter().map(|&k| (k, k)));
+    println!("rbtree extend: {:?}", common::timing(test_extend));
+    let mut hinted = RBTreeMap::new();
+    let test_hint = || {
+        let mut prev = data[0];
+        for &k in &data {
+            hinted.insert_hint(&prev, k, k);
+            prev = k;
+        }
+    };
+    println!("rbtree insert_hint: {:?}", common::timing(test_hint));
+    extended.check();
+    hinted.check();
+    assert!(extended.iter().eq(tree.iter()));
+    assert!(hinted.iter().eq(tree.iter()));
+    assert_eq!(map, extended);
+}
+
+#[test]
+fn rbtree_remove_range() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use std::ops::{Bound, RangeBounds};
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    let bound = |rng: &mut rand::rngs::ThreadRng| {
+        let k = rng.gen_range(0..2200);
+        match rng.gen_range(0..5) {
+            0 => Bound::Unbounded,
+            1 | 2 => Bound::Included(k),
+            _ => Bound::Excluded(k),
+        }
+    };
+    for _ in 0..50 {
+        let data = common::rand_data(rng.gen_range(0..2000), 0..2000);
+        let mut map = RBTreeMap::new();
+        map.extend(data.iter().map(|&k| (k, k)));
+        let mut std_map: BTreeMap<_, _> = data.iter().map(|&k| (k, k)).collect();
+        for _ in 0..20 {
+            let (start, end) = (bound(&mut rng), bound(&mut rng));
+            let removed = map.remove_range((start, end));
+            let expected: Vec<_> = std_map
+                .iter()
+                .map(|(&k, &v)| (k, v))
+                .filter(|(k, _)| (start, end).contains(k))
+                .collect();
+            for (k, _) in &expected {
+                std_map.remove(k);
+            }
+            assert_eq!(removed.validate(), Ok(()));
+            assert_eq!(map.validate(), Ok(()));
+            assert!(removed.into_iter().eq(expected));
+            assert!(map.iter().eq(std_map.iter()));
+            let k = rng.gen_range(0..2000);
+            assert_eq!(map.insert(k, k), std_map.insert(k, k));
+        }
+    }
+    // a transaction puts the removed entries back
+    let mut map = RBTreeMap::new();
+    map.extend((0..100).map(|i| (i, i)));
+    {
+        let mut tx = map.begin();
+        tx.clear_range(10..90);
+        assert_eq!(tx.len(), 20);
+    }
+    assert!(map.iter().map(|(k, _)| *k).eq(0..100));
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn rbtree_remove_range_timing() {
+    use xsl::collections::RBTreeMap;
+    // a narrow range out of a big map only touches a path's worth of nodes
+    let mut map = RBTreeMap::new();
+    map.extend((0..1000000).map(|i| (i, i)));
+    let elapsed = common::timing(|| {
+        for i in 0..1000 {
+            map.clear_range(i * 1000..i * 1000 + 10);
+        }
+    });
+    println!("remove_range x1000: {:?}", elapsed);
+    assert_eq!(map.len(), 990000);
+    assert_eq!(map.validate(), Ok(()));
+    // so does splitting a few entries off, though the rest move to the new map
+    let elapsed = common::timing(|| {
+        for _ in 0..1000 {
+            let (&first, _) = map.first_key_value().unwrap();
+            map = map.split_by(|&k| k < first + 10);
+        }
+    });
+    println!("split_by x1000: {:?}", elapsed);
+    assert_eq!(map.len(), 980000);
+    assert_eq!(map.validate(), Ok(()));
+}
+
+#[test]
+fn rbtree_split_by() {
+    use rand::Rng;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let data = common::rand_data(rng.gen_range(0..2000), 0..2000);
+        let mut sorted = data.clone();
+        sorted.sort();
+        let mut map = RBTreeMap::new();
+        map.extend(data.iter().map(|&k| (k, k)));
+        let pivot = rng.gen_range(-10..2010);
+        let point = sorted.partition_point(|&k| k < pivot);
+        assert_eq!(
+            map.partition_point(|&k| k < pivot),
+            sorted.get(point).map(|k| (k, k))
+        );
+        let high = map.split_by(|&k| k < pivot);
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(high.validate(), Ok(()));
+        assert!(map.iter().map(|(k, _)| k).eq(&sorted[..point]));
+        assert!(high.iter().map(|(k, _)| k).eq(&sorted[point..]));
+    }
+}
+
+#[test]
+fn rbtree_handle() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::rbtree_map::Entry;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    let mut map = RBTreeMap::new();
+    let mut std_map = BTreeMap::new();
+    let mut handles = Vec::new();
+    for _ in 0..20000 {
+        let k = rng.gen_range(0..500);
+        match rng.gen_range(0..10) {
+            0..=3 => match map.entry(k) {
+                Entry::Vacant(entry) => {
+                    handles.push((k, entry.insert_with_handle(k)));
+                    std_map.insert(k, k);
+                }
+                Entry::Occupied(mut entry) => handles.push((k, entry.handle())),
+            },
+            4 | 5 => assert_eq!(map.remove(&k), std_map.remove(&k)),
+            6 | 7 if !handles.is_empty() => {
+                let (k, handle) = handles.swap_remove(rng.gen_range(0..handles.len()));
+                assert_eq!(map.remove_by_handle(handle), std_map.remove_entry(&k));
+            }
+            8 => {
+                map.clear_range(k..k + 5);
+                std_map.retain(|&key, _| !(k..k + 5).contains(&key));
+            }
+            _ => {
+                if let Some((&key, value)) = handles
+                    .last()
+                    .and_then(|&(_, handle)| map.get_mut_by_handle(handle))
+                {
+                    *value += 1;
+                    *std_map.get_mut(&key).unwrap() += 1;
+                }
+            }
+        }
+        for &(k, handle) in &handles {
+            assert_eq!(map.get_by_handle(handle), std_map.get_key_value(&k));
+        }
+        handles.retain(|&(_, handle)| map.get_by_handle(handle).is_some());
+    }
+    assert_eq!(map.validate(), Ok(()));
+    assert!(map.iter().eq(std_map.iter()));
+    // a handle is not accepted by another map, even one with handles of its
+    // own from the same slots and generations
+    let mut other = map.clone();
+    for &(k, _) in &handles {
+        let Entry::Occupied(mut entry) = other.entry(k) else {
+            unreachable!()
+        };
+        entry.handle();
+    }
+    for &(_, handle) in &handles {
+        assert_eq!(other.get_by_handle(handle), None);
+    }
+    // nor by the map itself once cleared and handing out handles again
+    map.clear();
+    for k in 0..500 {
+        let Entry::Vacant(entry) = map.entry(k) else {
+            unreachable!()
+        };
+        entry.insert_with_handle(k);
+    }
+    for &(_, handle) in &handles {
+        assert_eq!(map.get_by_handle(handle), None);
+    }
+}
+
+#[test]
+fn rbtree_prefix_range() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use std::ops::Bound;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    // the edges of the char and byte ranges, where the upper bound is easy to get wrong
+    let chars = ['a', 'b', '/', '0', '\u{D7FF}', '\u{E000}', char::MAX];
+    let word = |rng: &mut rand::rngs::ThreadRng, len| -> String {
+        (0..rng.gen_range(0..len))
+            .map(|_| chars[rng.gen_range(0..chars.len())])
+            .collect()
+    };
+    let mut map = RBTreeMap::new();
+    let mut bytes = RBTreeMap::new();
+    for i in 0..2000 {
+        let key = word(&mut rng, 6);
+        bytes.insert(key.as_bytes().to_vec(), i);
+        map.insert(key, i);
+    }
+    let std_map: BTreeMap<_, _> = map.iter().map(|(k, v)| (k.clone(), *v)).collect();
+    for _ in 0..500 {
+        let prefix = word(&mut rng, 4);
+        let expected: Vec<_> = std_map
+            .iter()
+            .filter(|(k, _)| k.starts_with(&prefix))
+            .collect();
+        assert!(map
+            .prefix_range(prefix.as_str())
+            .eq(expected.iter().copied()));
+        assert!(map
+            .prefix_range(prefix.as_str())
+            .rev()
+            .eq(expected.iter().rev().copied()));
+        let count = bytes
+            .iter()
+            .filter(|(k, _)| k.starts_with(prefix.as_bytes()))
+            .count();
+        assert_eq!(bytes.prefix_range(prefix.as_bytes()).count(), count);
+        let (start, end) = (word(&mut rng, 3), word(&mut rng, 3));
+        let bounds = (
+            Bound::Included(start.as_str()),
+            Bound::Excluded(end.as_str()),
+        );
+        if start <= end {
+            assert!(map
+                .range::<str, _>(bounds)
+                .eq(std_map.range::<str, _>(bounds)));
+        }
+    }
+    let mut removed = 0;
+    for prefix in ["a", "\u{D7FF}", "/", ""] {
+        let count = map.prefix_range(prefix).count();
+        assert_eq!(map.remove_prefix(prefix).len(), count);
+        assert_eq!(map.prefix_range(prefix).next(), None);
+        assert_eq!(map.validate(), Ok(()));
+        removed += count;
+    }
+    assert_eq!(removed, std_map.len());
+    assert!(map.is_empty());
+    let count = bytes.prefix_range(&[0xF4][..]).count();
+    assert_eq!(bytes.remove_prefix(&[0xF4][..]).len(), count);
+    assert!(bytes.iter().all(|(k, _)| k.first() != Some(&0xF4)));
+}
+
+#[test]
+fn rbtree_diff() {
+    use rand::Rng;
+    use xsl::collections::rbtree_map::Change;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        let mut old = RBTreeMap::new();
+        old.extend((0..1000).map(|_| (rng.gen_range(0..2000), rng.gen_range(0..3))));
+        let mut new = old.clone();
+        for _ in 0..rng.gen_range(0..300) {
+            let k = rng.gen_range(0..2000);
+            match rng.gen_range(0..3) {
+                0 => {
+                    new.remove(&k);
+                }
+                _ => {
+                    new.insert(k, rng.gen_range(0..3));
+                }
+            }
+        }
+        let changes: Vec<_> = RBTreeMap::diff(&old, &new).collect();
+        assert!(changes.windows(2).all(|w| w[0].key() < w[1].key()));
+        for change in &changes {
+            match *change {
+                Change::Added(k, v) => assert!(!old.contains_key(k) && new[k] == *v),
+                Change::Removed(k, v) => assert!(old[k] == *v && !new.contains_key(k)),
+                Change::Changed(k, a, b) => assert!(a != b && old[k] == *a && new[k] == *b),
+            }
+        }
+        let unchanged = old.iter().filter(|(k, v)| new.get(k) == Some(v)).count();
+        let union = old.len() + new.iter().filter(|(k, _)| !old.contains_key(k)).count();
+        assert_eq!(unchanged + changes.len(), union);
+        let patch: Vec<_> = changes.into_iter().map(Change::cloned).collect();
+        let mut synced = old.clone();
+        synced.apply_patch(patch);
+        assert_eq!(synced.validate(), Ok(()));
+        assert_eq!(synced, new);
+        assert_eq!(RBTreeMap::diff(&synced, &new).next(), None);
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn durable_rbtree_map() {
+    use rand::Rng;
+    use std::io::Write;
+    use xsl::collections::durable::SyncPolicy;
+    use xsl::collections::{DurableRBTreeMap, RBTreeMap};
+    let path = std::env::temp_dir().join(format!("xsl-durable-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut rng = rand::thread_rng();
+    let mut expected = RBTreeMap::new();
+    {
+        let mut map = DurableRBTreeMap::open_with(&path, SyncPolicy::EveryN(64)).unwrap();
+        for _ in 0..5000 {
+            let k: u32 = rng.gen_range(0..1000);
+            match rng.gen_range(0..3) {
+                0 => assert_eq!(map.remove(&k).unwrap(), expected.remove(&k)),
+                _ => {
+                    let v = format!("{}", rng.gen::<u16>());
+                    assert_eq!(map.insert(k, v.clone()).unwrap(), expected.insert(k, v));
+                }
+            }
+        }
+        assert_eq!(*map, expected);
+    }
+    let mut map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+    assert_eq!(*map, expected);
+    assert!(map.log_len() > 0);
+    map.compact().unwrap();
+    assert_eq!(map.log_len(), 0);
+    map.insert(1000, "last".into()).unwrap();
+    expected.insert(1000, "last".into());
+    drop(map);
+    let len = std::fs::metadata(&path).unwrap().len();
+    let map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+    assert_eq!(map.validate(), Ok(()));
+    assert_eq!(*map, expected);
+    assert_eq!(map.log_len(), 1);
+    drop(map);
+    // a torn final record is cut off, keeping everything before it
+    let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+    file.set_len(len - 3).unwrap();
+    drop(file);
+    expected.remove(&1000);
+    let map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+    assert_eq!(*map, expected);
+    drop(map);
+    assert!(std::fs::metadata(&path).unwrap().len() < len - 3);
+    // as are garbage bytes that fail the checksum
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(&[9, 0, 0, 0, 1, 2, 3, 4, 1, 0, 0, 0, 0, 0, 0, 0, 0])
+        .unwrap();
+    drop(file);
+    let mut map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+    assert_eq!(*map, expected);
+    map.clear().unwrap();
+    drop(map);
+    assert!(DurableRBTreeMap::<u32, String>::open(&path)
+        .unwrap()
+        .is_empty());
+    // a failed append is cut off the log, so later records are not lost
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    {
+        /// Caps the size of files this process writes, so a write past the cap
+        /// fails partway, as on a full disk.
+        struct FileSizeLimit([u64; 2]);
+        const RLIMIT_FSIZE: i32 = 1;
+        const SIGXFSZ: i32 = 25;
+        const SIG_IGN: usize = 1;
+        extern "C" {
+            fn getrlimit(resource: i32, limit: *mut [u64; 2]) -> i32;
+            fn setrlimit(resource: i32, limit: *const [u64; 2]) -> i32;
+            fn signal(signal: i32, handler: usize) -> usize;
+        }
+        impl FileSizeLimit {
+            fn set(bytes: u64) -> Self {
+                let mut old = [0; 2];
+                unsafe {
+                    signal(SIGXFSZ, SIG_IGN);
+                    assert_eq!(getrlimit(RLIMIT_FSIZE, &mut old), 0);
+                    assert_eq!(setrlimit(RLIMIT_FSIZE, &[bytes, old[1]]), 0);
+                }
+                Self(old)
+            }
+        }
+        impl Drop for FileSizeLimit {
+            fn drop(&mut self) {
+                unsafe { setrlimit(RLIMIT_FSIZE, &self.0) };
+            }
+        }
+        let mut map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+        map.insert(1, "a".into()).unwrap();
+        let len = std::fs::metadata(&path).unwrap().len();
+        let limit = FileSizeLimit::set(len + 12);
+        assert!(map.insert(2, "b".repeat(100)).is_err());
+        drop(limit);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), len);
+        assert_eq!(map.get(&2), None);
+        map.insert(3, "c".into()).unwrap();
+        drop(map);
+        let map = DurableRBTreeMap::<u32, String>::open(&path).unwrap();
+        assert!(map.iter().eq([(&1, &"a".into()), (&3, &"c".into())]));
+    }
+    std::fs::write(&path, b"not a log").unwrap();
+    assert!(DurableRBTreeMap::<u32, String>::open(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rbtree_comparable() {
+    use rand::Rng;
+    use xsl::collections::rbtree_map::Parts;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    let names = ["ann", "bob", "cat", "dan", "eve"];
+    let mut map = RBTreeMap::new();
+    map.extend((0..2000).map(|i| {
+        let name = names[rng.gen_range(0..names.len())].to_string();
+        ((name, rng.gen_range(0..500u64)), i)
+    }));
+    for _ in 0..1000 {
+        let name = names[rng.gen_range(0..names.len())];
+        let id = rng.gen_range(0..500u64);
+        assert_eq!(
+            map.get_by(&Parts((name, &id))),
+            map.get(&(name.to_string(), id))
+        );
+        let expected: Vec<_> = map.iter().filter(|((n, _), _)| n == name).collect();
+        assert!(map
+            .range_by(Parts((name,))..=Parts((name,)))
+            .eq(expected.iter().copied()));
+        let expected = map
+            .iter()
+            .filter(|((n, i), _)| n == name && *i < id)
+            .count();
+        assert_eq!(
+            map.range_by(Parts((name, &0))..Parts((name, &id))).count(),
+            expected
+        );
+    }
+    let before = map.len();
+    let bobs = map.iter().filter(|((n, _), _)| n == "bob").count();
+    let removed = map.remove_range_by(Parts(("bob",))..=Parts(("bob",)));
+    assert_eq!(removed.len(), bobs);
+    assert_eq!(map.len(), before - bobs);
+    assert_eq!(map.validate(), Ok(()));
+    assert!(map
+        .range_by(Parts(("bob",))..=Parts(("bob",)))
+        .next()
+        .is_none());
+    let (key, value) = map.first_key_value().map(|(k, v)| (k.clone(), *v)).unwrap();
+    assert_eq!(map.remove_by(&Parts((key.0.as_str(), &key.1))), Some(value));
+    assert!(!map.contains_key(&key));
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn small_rbtree_map_timing() {
+    use xsl::collections::{RBTreeMap, SmallRBTreeMap};
+    // Build many maps of each size, with the default `N` and as plain trees,
+    // then probe every key and report the memory each map takes.
+    fn run<V: Default>(name: &str) {
+        for n in [1, 2, 3, 4, 6, 8, 12, 16] {
+            let data: Vec<Vec<u64>> = (0..100000 / n)
+                .map(|_| {
+                    common::rand_data(n, 0..1000)
+                        .into_iter()
+                        .map(|k| k as u64)
+                        .collect()
+                })
+                .collect();
+            let small: Vec<_> = data
+                .iter()
+                .map(|keys| {
+                    let mut map = SmallRBTreeMap::<_, V>::new();
+                    map.extend(keys.iter().map(|&k| (k, V::default())));
+                    map
+                })
+                .collect();
+            let trees: Vec<_> = data
+                .iter()
+                .map(|keys| {
+                    let mut map = RBTreeMap::new();
+                    map.extend(keys.iter().map(|&k| (k, V::default())));
+                    map
+                })
+                .collect();
+            let small_time = common::timing(|| {
+                for (map, keys) in small.iter().zip(&data) {
+                    for k in keys {
+                        assert!(map.get(k).is_some());
+                    }
+                }
+            });
+            let tree_time = common::timing(|| {
+                for (map, keys) in trees.iter().zip(&data) {
+                    for k in keys {
+                        assert!(map.get(k).is_some());
+                    }
+                }
+            });
+            let tree_bytes = size_of_val(&trees[0]) + trees[0].stats().allocated_bytes;
+            let (mode, small_bytes) = if small[0].is_inline() {
+                ("inline", size_of_val(&small[0]))
+            } else {
+                (
+                    "spilled",
+                    size_of_val(&small[0]) + trees[0].stats().allocated_bytes,
+                )
+            };
+            println!(
+                "(u64, {name}) x {n}: {mode} {small_time:?} {small_bytes} bytes, rbtree {tree_time:?} {tree_bytes} bytes"
+            );
+        }
+    }
+    run::<u64>("u64");
+    run::<[u64; 7]>("[u64; 7]");
+}
+
+#[test]
+fn small_rbtree_map_size() {
+    use xsl::collections::{RBTreeMap, SmallRBTreeMap};
+    // `small_rbtree_map_timing` finds lookups in the array faster than in a
+    // tree from two entries on, so memory picks the default `N`: eight
+    // `(u64, u64)` entries inline take no more room than a tree of two, and a
+    // map that has spilled carries less than two nodes on top of a plain tree.
+    let tree_bytes = |n: u64| {
+        let mut map = RBTreeMap::new();
+        map.extend((0..n).map(|k| (k, k)));
+        size_of_val(&map) + map.stats().allocated_bytes
+    };
+    let small = size_of::<SmallRBTreeMap<u64, u64>>();
+    assert!(small <= tree_bytes(2));
+    assert!(small - size_of::<RBTreeMap<u64, u64>>() < tree_bytes(2) - tree_bytes(0));
+    let mut map = SmallRBTreeMap::<u64, u64>::new();
+    map.extend((0..8).map(|k| (k, k)));
+    assert!(map.is_inline());
+    map.insert(8, 8);
+    assert!(!map.is_inline());
 }
 
 #[test]
@@ -75,3 +770,906 @@ fn fuzzy_finder() {
     assert_eq!(finder.search_prefix("e".to_string()), vec![&2, &1]);
     assert_eq!(finder.search_prefix("w".to_string()), Vec::<&i32>::new());
 }
+
+#[test]
+fn rbtree_merge() {
+    use std::collections::BTreeMap;
+    use xsl::collections::{rbtree_map::MergeJoinItem, RBTreeMap};
+    let left_data = common::rand_data(1000, 0..2000);
+    let right_data = common::rand_data(1000, 0..2000);
+    let build = |data: &[i32], sign: i32| {
+        let mut map = RBTreeMap::new();
+        map.extend(data.iter().map(|&k| (k, k * sign)));
+        map
+    };
+    let std_left: BTreeMap<_, _> = left_data.iter().map(|&k| (k, k)).collect();
+    let std_right: BTreeMap<_, _> = right_data.iter().map(|&k| (k, -k)).collect();
+
+    let (left, right) = (build(&left_data, 1), build(&right_data, -1));
+    let mut both = 0;
+    for item in left.merge_join(&right) {
+        match item {
+            MergeJoinItem::Left(k, _) => assert!(!std_right.contains_key(k)),
+            MergeJoinItem::Right(k, _) => assert!(!std_left.contains_key(k)),
+            MergeJoinItem::Both(k, l, r) => {
+                assert_eq!(*l, -*r);
+                assert!(std_left.contains_key(k) && std_right.contains_key(k));
+                both += 1;
+            }
+        }
+    }
+
+    let union = left.union_with(right, |_, l, r| l + r);
+    union.check();
+    assert_eq!(union.len(), 2000 - both);
+    let (left, right) = (build(&left_data, 1), build(&right_data, -1));
+    let intersection = left.intersection_with(right, |_, l, r| (l, r));
+    intersection.check();
+    assert_eq!(intersection.len(), both);
+    let (left, right) = (build(&left_data, 1), build(&right_data, -1));
+    let difference = left.difference(right);
+    difference.check();
+    assert!(difference
+        .iter()
+        .eq(std_left.iter().filter(|(k, _)| !std_right.contains_key(k))));
+}
+
+#[test]
+fn indexed_priority_queue() {
+    use rand::Rng;
+    let mut rng = rand::thread_rng();
+    let mut queue = xsl::collections::IndexedPriorityQueue::new();
+    let mut model = std::collections::BTreeMap::new();
+    let mut order = 0;
+    for _ in 0..10000 {
+        let id = rng.gen_range(0..100);
+        let priority = rng.gen_range(0..10);
+        match rng.gen_range(0..4) {
+            0 => {
+                let old = model.get(&id).map(|&(p, _)| p);
+                let seq = model.get(&id).map_or(order, |&(_, s)| s);
+                order += 1;
+                model.insert(id, (priority, seq));
+                assert_eq!(queue.push(id, priority), old);
+            }
+            1 => {
+                let old = model
+                    .get_mut(&id)
+                    .map(|e| std::mem::replace(&mut e.0, priority));
+                assert_eq!(queue.change_priority(&id, priority), old);
+            }
+            2 => {
+                assert_eq!(queue.remove(&id), model.remove(&id).map(|(p, _)| p));
+            }
+            _ => {
+                let min = model
+                    .iter()
+                    .min_by_key(|(_, &key)| key)
+                    .map(|(&id, &(p, _))| (id, p));
+                if let Some((id, _)) = min {
+                    model.remove(&id);
+                }
+                assert_eq!(queue.pop_min(), min);
+            }
+        }
+        assert_eq!(queue.len(), model.len());
+    }
+
+    // ids and priorities need not be `Clone`
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct Job(String);
+    let mut queue = xsl::collections::IndexedPriorityQueue::new();
+    for (i, name) in ["c", "a", "b"].into_iter().enumerate() {
+        queue.push(Job(name.to_string()), Job(i.to_string()));
+    }
+    assert_eq!(
+        queue.change_priority(&Job("b".to_string()), Job("".to_string())),
+        Some(Job("2".to_string()))
+    );
+    assert_eq!(
+        queue.priority(&Job("a".to_string())),
+        Some(&Job("1".to_string()))
+    );
+    assert_eq!(queue.peek().map(|(id, _)| id), Some(&Job("b".to_string())));
+    let order: Vec<_> = std::iter::from_fn(|| queue.pop_min())
+        .map(|(id, _)| id.0)
+        .collect();
+    assert_eq!(order, ["b", "c", "a"]);
+}
+
+#[test]
+fn expiring_map() {
+    use std::cell::Cell;
+    use xsl::collections::{Clock, ExpiringMap};
+    struct Ticks<'a>(&'a Cell<u32>);
+    impl Clock for Ticks<'_> {
+        type Instant = u32;
+        type Duration = u32;
+        fn now(&self) -> u32 {
+            self.0.get()
+        }
+    }
+    let time = Cell::new(0);
+    let mut map = ExpiringMap::new(Ticks(&time));
+    for k in 0..100 {
+        map.insert_with_ttl(k, k, k % 10 + 1);
+    }
+    time.set(5);
+    assert_eq!(map.get(&4), None);
+    assert_eq!(map.get(&5), Some(&5));
+    assert_eq!(map.remove(&3), None);
+    assert_eq!(map.remove(&9), Some(9));
+    assert_eq!(map.purge_expired(5), 49);
+    assert_eq!(map.len(), 49);
+    assert_eq!(map.next_expiry(), Some(&6));
+    assert_eq!(map.insert_with_ttl(15, 0, 100), Some(15));
+    time.set(10);
+    assert_eq!(map.purge_expired(10), 48);
+    assert_eq!(map.len(), 1);
+    assert!((0..100).all(|k| map.contains_key(&k) == (k == 15)));
+    // re-inserting with the same deadline keeps the deadline record
+    assert_eq!(map.insert_with_ttl(200, 1, 5), None);
+    assert_eq!(map.insert_with_ttl(200, 2, 5), Some(1));
+    assert_eq!(map.next_expiry(), Some(&15));
+    time.set(200);
+    assert_eq!(map.purge_expired(200), 2);
+    assert!(map.is_empty());
+}
+
+#[test]
+fn rbtree_observer() {
+    use std::{cell::RefCell, collections::BTreeMap, collections::BTreeSet, rc::Rc};
+    use xsl::collections::{rbtree_map::Observer, RBTreeMap};
+    #[derive(Default)]
+    struct Mirror {
+        map: BTreeMap<i32, i32>,
+        dirty: BTreeSet<i32>,
+    }
+    struct Hook(Rc<RefCell<Mirror>>);
+    impl Observer<i32, i32> for Hook {
+        fn on_insert(&mut self, key: &i32, value: &i32) {
+            assert!(self.0.borrow_mut().map.insert(*key, *value).is_none());
+        }
+        fn on_update(&mut self, key: &i32, old: &i32, new: &i32) {
+            assert_eq!(self.0.borrow_mut().map.insert(*key, *new), Some(*old));
+        }
+        fn on_remove(&mut self, key: &i32, value: &i32) {
+            assert_eq!(self.0.borrow_mut().map.remove(key), Some(*value));
+        }
+        fn on_get_mut(&mut self, key: &i32, _value: &i32) {
+            self.0.borrow_mut().dirty.insert(*key);
+        }
+    }
+    let mirror = Rc::new(RefCell::new(Mirror::default()));
+    let mut map = RBTreeMap::new();
+    map.set_observer(Hook(mirror.clone()));
+    for k in common::rand_data(1000, 0..2000) {
+        map.insert(k % 500, k);
+    }
+    for k in common::rand_data(300, 0..600) {
+        map.remove(&k);
+    }
+    map.pop_first();
+    map.pop_last();
+    map.entry(-1).or_insert(0);
+    for v in map.values_mut().take(10) {
+        *v += 1;
+    }
+    {
+        let mut mirror = mirror.borrow_mut();
+        let dirty = std::mem::take(&mut mirror.dirty);
+        assert_eq!(dirty.len(), 10);
+        for k in dirty {
+            mirror.map.insert(k, map[&k]);
+        }
+        assert!(mirror.map.iter().eq(map.iter()));
+    }
+    map.clear();
+    assert!(mirror.borrow().map.is_empty());
+}
+
+#[test]
+fn rbtree_transaction() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    let mut map = RBTreeMap::new();
+    map.extend(common::rand_data(500, 0..1000).into_iter().map(|k| (k, k)));
+    let before: BTreeMap<_, _> = map.iter().map(|(&k, &v)| (k, v)).collect();
+    let mut edit = |map: &mut RBTreeMap<i32, i32>| {
+        for _ in 0..200 {
+            let k = rng.gen_range(0..1000);
+            match rng.gen_range(0..4) {
+                0 => {
+                    map.insert(k, -k);
+                }
+                1 => {
+                    map.remove(&k);
+                }
+                2 => {
+                    if let Some(v) = map.get_mut(&k) {
+                        *v += 1;
+                    }
+                }
+                _ => {
+                    map.pop_first();
+                }
+            }
+        }
+    };
+    {
+        let mut tx = map.begin();
+        edit(&mut tx);
+        let middle: BTreeMap<_, _> = tx.iter().map(|(&k, &v)| (k, v)).collect();
+        {
+            let mut inner = tx.begin();
+            edit(&mut inner);
+            inner.values_mut().for_each(|v| *v = 0);
+            inner.rollback();
+        }
+        assert!(tx.iter().eq(middle.iter()));
+        {
+            let mut inner = tx.begin();
+            edit(&mut inner);
+            inner.commit();
+        }
+        tx.clear();
+        // dropped without commit
+    }
+    map.check();
+    assert!(map.iter().eq(before.iter()));
+    let mut tx = map.begin();
+    tx.insert(-1, 1);
+    tx.commit();
+    assert_eq!(map.len(), before.len() + 1);
+    // replacing the map through the guard ends the recording without a panic
+    {
+        let mut tx = map.begin();
+        tx.insert(-2, 2);
+        let mut inner = tx.begin();
+        *inner = RBTreeMap::from([(7, 7)]);
+        inner.insert(8, 8);
+        inner.rollback();
+        tx.insert(9, 9);
+    }
+    assert!(map.iter().eq([(&7, &7), (&8, &8), (&9, &9)]));
+    let mut tx = map.begin();
+    std::mem::take(&mut *tx);
+    tx.commit();
+    assert!(map.is_empty());
+    let mut tx = map.begin();
+    tx.insert(1, 1);
+    tx.rollback();
+    assert!(map.is_empty());
+}
+
+#[test]
+fn rbtree_validate() {
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use xsl::collections::{rbtree_map::InvariantViolation, RBTreeMap};
+    thread_local! {
+        static REVERSED: Cell<bool> = const { Cell::new(false) };
+    }
+    #[derive(Debug, PartialEq, Eq)]
+    struct Fickle(i32);
+    impl Ord for Fickle {
+        fn cmp(&self, other: &Self) -> Ordering {
+            let ord = self.0.cmp(&other.0);
+            if REVERSED.get() {
+                ord.reverse()
+            } else {
+                ord
+            }
+        }
+    }
+    impl PartialOrd for Fickle {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    let mut map = RBTreeMap::new();
+    map.extend(
+        common::rand_data(100, 0..1000)
+            .into_iter()
+            .map(|k| (Fickle(k), ())),
+    );
+    assert_eq!(map.validate(), Ok(()));
+    REVERSED.set(true);
+    let violation = map.validate().unwrap_err();
+    assert!(matches!(violation, InvariantViolation::Order { .. }));
+    assert!(violation.key().is_some());
+    REVERSED.set(false);
+    assert_eq!(map.validate(), Ok(()));
+}
+
+#[test]
+fn rbtree_allocator() {
+    use allocator_api2::alloc::{AllocError, Allocator, Global};
+    use std::{alloc::Layout, cell::Cell, ptr::NonNull, rc::Rc};
+    use xsl::collections::RBTreeMap;
+    #[derive(Clone, Default)]
+    struct Counting(Rc<Cell<usize>>);
+    unsafe impl Allocator for Counting {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            self.0.set(self.0.get() + layout.size());
+            Global.allocate(layout)
+        }
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.0.set(self.0.get() - layout.size());
+            Global.deallocate(ptr, layout)
+        }
+    }
+    let arena = Counting::default();
+    let mut map = RBTreeMap::new_in(arena.clone());
+    for k in common::rand_data(300, 0..1000) {
+        map.insert(k.to_string(), k);
+    }
+    map.check();
+    assert_eq!(arena.0.get(), map.stats().allocated_bytes);
+    // an observer and a transaction's undo log come from the map's allocator
+    // too, and its stats count them
+    struct Quiet;
+    impl xsl::collections::rbtree_map::Observer<String, i32> for Quiet {}
+    map.set_observer(Quiet);
+    {
+        let mut tx = map.begin();
+        tx.insert("x".to_string(), 0);
+        assert_eq!(arena.0.get(), tx.stats().allocated_bytes);
+    }
+    assert_eq!(arena.0.get(), map.stats().allocated_bytes);
+    assert!(map.take_observer().is_some());
+    assert_eq!(arena.0.get(), map.stats().allocated_bytes);
+    let copy = map.clone();
+    copy.check();
+    assert_eq!(map, copy);
+    let first = map.first_key_value().unwrap().0.clone();
+    assert_eq!(copy[&first], map[&first]);
+    assert_eq!(format!("{map}"), format!("{copy}"));
+    let other = Counting::default();
+    let moved = copy.clone_in(other.clone());
+    drop(copy);
+    assert_eq!(other.0.get(), arena.0.get());
+    let from = RBTreeMap::from_in([(2, 'b'), (1, 'a')], arena.clone());
+    assert!(from.into_iter().eq([(1, 'a'), (2, 'b')]));
+    // a bulk build allocates its nodes and nothing else, and frees them if the
+    // iterator panics part way
+    let before = arena.0.get();
+    let built =
+        RBTreeMap::bulk_build_from_sorted_iter([(1, 'a'), (1, 'b'), (2, 'c')], arena.clone());
+    built.check();
+    assert!(built.iter().eq([(&1, &'b'), (&2, &'c')]));
+    assert_eq!(arena.0.get() - before, built.stats().allocated_bytes);
+    drop(built);
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let entries = (0..10).map(|k| if k < 5 { (k, k) } else { panic!() });
+        RBTreeMap::bulk_build_from_sorted_iter(entries, arena.clone())
+    }));
+    assert!(result.is_err());
+    assert_eq!(arena.0.get(), before);
+    assert!(moved.iter().eq(map.iter()));
+    // the handle table comes from the map's allocator, counts in its stats and
+    // goes with the map
+    let handle = match map.entry(first.clone()) {
+        xsl::collections::rbtree_map::Entry::Occupied(mut entry) => entry.handle(),
+        _ => unreachable!(),
+    };
+    assert_eq!(arena.0.get(), map.stats().allocated_bytes);
+    assert_eq!(map.get_by_handle(handle).map(|(k, _)| k), Some(&first));
+    drop(map);
+    assert_eq!(arena.0.get(), 0);
+    drop(moved);
+    assert_eq!(other.0.get(), 0);
+}
+
+#[test]
+fn frozen_map() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::RBTreeMap;
+    let mut rng = rand::thread_rng();
+    for len in [0, 1, 2, 3, 7, 8, 100, 1000] {
+        let data = common::rand_data(len, 0..2000);
+        let std_map: BTreeMap<_, _> = data.iter().map(|&k| (k, -k)).collect();
+        let mut map = RBTreeMap::new();
+        map.extend(data.iter().map(|&k| (k, -k)));
+        let frozen = map.freeze();
+        assert_eq!(frozen.len(), len);
+        assert!(frozen.iter().eq(std_map.iter()));
+        assert!(frozen.iter().rev().eq(std_map.iter().rev()));
+        for _ in 0..200 {
+            let k = rng.gen_range(-10..2010);
+            assert_eq!(frozen.get(&k), std_map.get(&k));
+            assert_eq!(frozen.rank(&k), std_map.range(..k).count());
+            let end = rng.gen_range(-10..2010);
+            if k <= end {
+                assert!(frozen.range(k..end).eq(std_map.range(k..end)));
+                assert!(frozen.range(k..=end).eq(std_map.range(k..=end)));
+            }
+        }
+        let thawed = frozen.thaw();
+        thawed.check();
+        assert!(thawed.iter().eq(std_map.iter()));
+    }
+}
+
+#[cfg(not(feature = "paranoid"))]
+#[test]
+fn frozen_map_timing() {
+    let data = common::rand_data(1000000, 0..1000000);
+    let tree: std::collections::BTreeMap<_, _> = data.iter().map(|&k| (k, 0)).collect();
+    let test_find = || {
+        for k in &data {
+            tree.get(k);
+        }
+    };
+    println!("btree find: {:?}", common::timing(test_find));
+    let mut map = xsl::collections::RBTreeMap::new();
+    map.extend(data.iter().map(|&k| (k, 0)));
+    let test_find = || {
+        for k in &data {
+            map.get(k);
+        }
+    };
+    println!("rbtree find: {:?}", common::timing(test_find));
+    let frozen = map.freeze();
+    let test_find = || {
+        for k in &data {
+            frozen.get(k);
+        }
+    };
+    println!("frozen find: {:?}", common::timing(test_find));
+}
+
+#[test]
+fn static_rbtree_map() {
+    use rand::Rng;
+    use std::{collections::BTreeMap, rc::Rc};
+    use xsl::collections::static_map::Entry as StaticEntry;
+    use xsl::collections::StaticRBTreeMap;
+    let mut rng = rand::thread_rng();
+    let token = Rc::new(());
+    let mut map = StaticRBTreeMap::<i32, Rc<()>, 64>::new();
+    let mut std_map = BTreeMap::new();
+    for _ in 0..20000 {
+        let k = rng.gen_range(0..100);
+        match rng.gen_range(0..6) {
+            0..=2 => {
+                let result = map.insert(k, token.clone());
+                if std_map.len() == 64 && !std_map.contains_key(&k) {
+                    assert!(result.is_err());
+                } else {
+                    assert_eq!(result.unwrap().is_some(), std_map.insert(k, ()).is_some());
+                }
+            }
+            3 => assert_eq!(map.remove(&k).is_some(), std_map.remove(&k).is_some()),
+            4 if k % 2 == 0 => match map.entry(k) {
+                StaticEntry::Occupied(entry) => {
+                    assert_eq!(std_map.remove(entry.key()), Some(()));
+                    entry.remove();
+                }
+                StaticEntry::Vacant(entry) => {
+                    let full = std_map.len() == 64;
+                    assert_eq!(entry.insert(token.clone()).is_err(), full);
+                    if !full {
+                        std_map.insert(k, ());
+                    }
+                }
+            },
+            4 => assert_eq!(
+                map.pop_first().map(|(k, _)| k),
+                std_map.pop_first().map(|(k, _)| k)
+            ),
+            _ => assert_eq!(
+                map.pop_last().map(|(k, _)| k),
+                std_map.pop_last().map(|(k, _)| k)
+            ),
+        }
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.len(), std_map.len());
+        assert_eq!(Rc::strong_count(&token), map.len() + 1);
+        assert!(map.iter().map(|(k, _)| k).eq(std_map.keys()));
+        assert!(map.iter().rev().map(|(k, _)| k).eq(std_map.keys().rev()));
+        let (a, b) = (rng.gen_range(0..100), rng.gen_range(0..100));
+        if a <= b {
+            assert!(map
+                .range(a..b)
+                .map(|(k, _)| k)
+                .eq(std_map.range(a..b).map(|(k, _)| k)));
+            assert!(map
+                .range(a..=b)
+                .rev()
+                .map(|(k, _)| k)
+                .eq(std_map.range(a..=b).rev().map(|(k, _)| k)));
+        }
+        if std_map.contains_key(&k) {
+            assert!(Rc::ptr_eq(&map[&k], &token));
+        }
+    }
+    for (_, v) in map.iter_mut() {
+        *v = Rc::new(());
+    }
+    assert_eq!(Rc::strong_count(&token), 1);
+    // every value borrowed at once, taken from both ends
+    let len = map.len();
+    let mut iter = map.iter_mut();
+    let mut values = Vec::new();
+    while let (Some((_, a)), b) = (iter.next(), iter.next_back()) {
+        values.push(a);
+        values.extend(b.map(|(_, b)| b));
+    }
+    assert_eq!(values.len(), len);
+    values.into_iter().for_each(|v| *v = token.clone());
+    assert_eq!(Rc::strong_count(&token), map.len() + 1);
+    map.clear();
+    assert!(map.is_empty());
+    map.insert(1, token.clone()).unwrap();
+    drop(map);
+    assert_eq!(Rc::strong_count(&token), 1);
+}
+
+#[test]
+fn small_rbtree_map() {
+    use rand::Rng;
+    use std::{collections::BTreeMap, rc::Rc};
+    use xsl::collections::small_map::Entry;
+    use xsl::collections::SmallRBTreeMap;
+    let mut rng = rand::thread_rng();
+    let token = Rc::new(());
+    for _ in 0..200 {
+        let mut map = SmallRBTreeMap::<i32, (i32, Rc<()>), 6>::new();
+        let mut std_map = BTreeMap::new();
+        let size = rng.gen_range(1..20);
+        for _ in 0..200 {
+            let k = rng.gen_range(0..size);
+            let v = rng.gen_range(0..100);
+            match rng.gen_range(0..6) {
+                0 | 1 => assert_eq!(
+                    map.insert(k, (v, token.clone())).map(|(v, _)| v),
+                    std_map.insert(k, v)
+                ),
+                2 => match map.entry(k) {
+                    Entry::Occupied(mut entry) => {
+                        assert_eq!(entry.get().0, std_map[&k]);
+                        if v % 2 == 0 {
+                            assert_eq!(entry.remove().0, std_map.remove(&k).unwrap());
+                        } else {
+                            entry.get_mut().0 = v;
+                            std_map.insert(k, v);
+                        }
+                    }
+                    Entry::Vacant(entry) => {
+                        assert!(!std_map.contains_key(&k));
+                        entry.insert((v, token.clone()));
+                        std_map.insert(k, v);
+                    }
+                },
+                3 => assert_eq!(map.remove(&k).map(|(v, _)| v), std_map.remove(&k)),
+                4 => assert_eq!(
+                    map.pop_first().map(|(k, (v, _))| (k, v)),
+                    std_map.pop_first()
+                ),
+                _ => assert_eq!(map.pop_last().map(|(k, (v, _))| (k, v)), std_map.pop_last()),
+            }
+            assert_eq!(map.len(), std_map.len());
+            assert_eq!(Rc::strong_count(&token), map.len() + 1);
+            assert!(map.iter().map(|(k, (v, _))| (k, v)).eq(std_map.iter()));
+            assert!(map.iter().rev().map(|(k, _)| k).eq(std_map.keys().rev()));
+            let (a, b) = (rng.gen_range(0..size), rng.gen_range(0..size));
+            let expected = std_map.keys().filter(|&&k| a <= k && k <= b);
+            assert!(map.range(a..=b).map(|(k, _)| k).eq(expected));
+            assert_eq!(map.get(&a).map(|(v, _)| v), std_map.get(&a));
+            if std_map.len() > 6 {
+                assert!(!map.is_inline());
+            }
+        }
+        let copy = map.clone();
+        assert_eq!(copy, map);
+        for (_, (v, _)) in map.iter_mut() {
+            *v += 1;
+        }
+        assert!(map
+            .iter()
+            .zip(copy.iter())
+            .all(|(a, b)| a.1 .0 == b.1 .0 + 1));
+        drop(copy);
+        map.clear();
+        assert!(map.is_inline() && map.is_empty());
+        map.insert(1, (1, token.clone()));
+    }
+    assert_eq!(Rc::strong_count(&token), 1);
+}
+
+#[test]
+fn multi_index_map() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::multi_index::OrderedIndex;
+    use xsl::collections::MultiIndexMap;
+    #[derive(Debug, Clone, PartialEq)]
+    struct Record {
+        id: i32,
+        group: i32,
+    }
+    struct ById;
+    impl OrderedIndex<Record> for ById {
+        type Key = i32;
+        const UNIQUE: bool = true;
+        fn key(record: &Record) -> i32 {
+            record.id
+        }
+    }
+    struct ByGroup;
+    impl OrderedIndex<Record> for ByGroup {
+        type Key = i32;
+        fn key(record: &Record) -> i32 {
+            record.group
+        }
+    }
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let mut map = MultiIndexMap::new()
+            .with_index::<ById>()
+            .with_index::<ByGroup>();
+        let mut model = BTreeMap::new();
+        let size = rng.gen_range(1..50);
+        for _ in 0..300 {
+            let id = rng.gen_range(0..size);
+            let group = rng.gen_range(0..size / 4 + 1);
+            match rng.gen_range(0..6) {
+                0 | 1 => {
+                    let result = map.insert(Record { id, group });
+                    assert_eq!(result.is_ok(), !model.contains_key(&id));
+                    model.entry(id).or_insert(group);
+                }
+                2 => assert_eq!(
+                    map.remove_by::<ById>(&id).map(|r| r.group),
+                    model.remove(&id)
+                ),
+                3 => {
+                    let removed = map.remove_by::<ByGroup>(&group);
+                    assert_eq!(removed.is_some(), model.values().any(|&g| g == group));
+                    if let Some(record) = removed {
+                        assert_eq!(record.group, group);
+                        assert_eq!(model.remove(&record.id), Some(group));
+                    }
+                }
+                4 => {
+                    let result = map.modify_by::<ById, _>(&id, |r| r.group = group);
+                    assert_eq!(result, Ok(model.contains_key(&id)));
+                    model.entry(id).and_modify(|g| *g = group);
+                }
+                _ => {
+                    let old = map.get_by::<ByGroup>(&group).map(|r| r.id);
+                    match map.modify_by::<ByGroup, _>(&group, |r| r.id = id) {
+                        Ok(modified) => {
+                            assert_eq!(modified, old.is_some());
+                            if let Some(old) = old {
+                                model.remove(&old);
+                                model.insert(id, group);
+                            }
+                        }
+                        // the refused change is handed back and the record
+                        // stays as it was
+                        Err(record) => {
+                            assert_eq!(record, Record { id, group });
+                            let old = old.unwrap();
+                            assert!(old != id && model.contains_key(&id));
+                            assert_eq!(map.get_by::<ById>(&old), Some(&Record { id: old, group }));
+                        }
+                    }
+                }
+            }
+            assert_eq!(map.len(), model.len());
+            assert!(map
+                .iter_by::<ById>()
+                .map(|r| (r.id, r.group))
+                .eq(model.clone()));
+            assert!(map
+                .iter_by::<ById>()
+                .rev()
+                .map(|r| r.id)
+                .eq(model.keys().rev().copied()));
+            let (a, b) = (rng.gen_range(0..size), rng.gen_range(0..size));
+            assert!(map
+                .range_by::<ById, _>(a..b)
+                .map(|r| r.id)
+                .eq(model.keys().copied().filter(|&k| a <= k && k < b)));
+        }
+        let mut by_group: Vec<_> = map.iter_by::<ById>().map(|r| (r.group, r.id)).collect();
+        by_group.sort();
+        // index records that are already present
+        map = MultiIndexMap::new().with_index::<ById>();
+        for &(group, id) in &by_group {
+            map.insert(Record { id, group }).unwrap();
+        }
+        map = map.with_index::<ByGroup>();
+        let groups: Vec<_> = map.iter_by::<ByGroup>().map(|r| r.group).collect();
+        assert!(groups.iter().copied().eq(by_group.iter().map(|(g, _)| *g)));
+        for (group, id) in by_group {
+            let mut ids: Vec<_> = map
+                .range_by::<ByGroup, _>(group..=group)
+                .map(|r| r.id)
+                .collect();
+            ids.sort();
+            assert!(ids.binary_search(&id).is_ok());
+        }
+        map.clear();
+        assert!(map.is_empty() && map.iter().next().is_none());
+    }
+    // a panic halfway through a modification takes the record out of every index
+    let mut map = MultiIndexMap::new()
+        .with_index::<ById>()
+        .with_index::<ByGroup>();
+    for id in 0..10 {
+        map.insert(Record { id, group: id % 3 }).unwrap();
+    }
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        map.modify_by::<ById, _>(&4, |r| {
+            r.id = 40;
+            r.group = 7;
+            panic!("halfway");
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(map.len(), 9);
+    assert!(map.get_by::<ById>(&4).is_none() && map.get_by::<ById>(&40).is_none());
+    assert!(map
+        .iter_by::<ById>()
+        .map(|r| r.id)
+        .eq((0..10).filter(|&id| id != 4)));
+    assert!(map.range_by::<ByGroup, _>(1..=1).map(|r| r.id).eq([1, 7]));
+    assert!(map.get_by::<ByGroup>(&7).is_none());
+    map.insert(Record { id: 4, group: 1 }).unwrap();
+    assert_eq!(map.iter().count(), 10);
+}
+
+#[test]
+fn rbtree_bimap() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::bimap::Overwritten;
+    use xsl::collections::RBTreeBiMap;
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let mut map = RBTreeBiMap::new();
+        let mut left = BTreeMap::new();
+        let mut right = BTreeMap::new();
+        let size = rng.gen_range(1..50);
+        for _ in 0..300 {
+            let (l, r) = (rng.gen_range(0..size), rng.gen_range(0..size) as u8);
+            match rng.gen_range(0..5) {
+                0 | 1 => {
+                    let by_left = left.remove(&l).map(|r| (l, r));
+                    if let Some((_, r)) = by_left {
+                        right.remove(&r);
+                    }
+                    let by_right = right.remove(&r).map(|l| (l, r));
+                    if let Some((l, _)) = by_right {
+                        left.remove(&l);
+                    }
+                    let expected = match (by_left, by_right) {
+                        (None, None) => Overwritten::Neither,
+                        (Some(pair), None) if pair == (l, r) => Overwritten::Pair(l, r),
+                        (Some((l, r)), None) => Overwritten::Left(l, r),
+                        (None, Some((l, r))) => Overwritten::Right(l, r),
+                        (Some(a), Some(b)) => Overwritten::Both(a, b),
+                    };
+                    assert_eq!(map.insert(l, r), expected);
+                    left.insert(l, r);
+                    right.insert(r, l);
+                }
+                2 => {
+                    let free = !left.contains_key(&l) && !right.contains_key(&r);
+                    assert_eq!(map.insert_no_overwrite(l, r).is_ok(), free);
+                    if free {
+                        left.insert(l, r);
+                        right.insert(r, l);
+                    }
+                }
+                3 => {
+                    let expected = left.remove(&l).map(|r| (l, r));
+                    if let Some((_, r)) = expected {
+                        right.remove(&r);
+                    }
+                    assert_eq!(map.remove_by_left(&l), expected);
+                }
+                _ => {
+                    let expected = right.remove(&r).map(|l| (l, r));
+                    if let Some((l, _)) = expected {
+                        left.remove(&l);
+                    }
+                    assert_eq!(map.remove_by_right(&r), expected);
+                }
+            }
+            assert_eq!(map.len(), left.len());
+            assert!(map.iter_left().eq(left.iter()));
+            assert!(map.iter_right().rev().eq(right.iter().rev()));
+            assert_eq!(map.get_by_left(&l), left.get(&l));
+            assert_eq!(map.get_by_right(&r), right.get(&r));
+            let (a, b) = (rng.gen_range(0..size), rng.gen_range(0..size));
+            assert!(map
+                .range_by_left(a..=b)
+                .eq(left.iter().filter(|(l, _)| (a..=b).contains(*l))));
+            let (a, b) = (a as u8, b as u8);
+            assert!(map
+                .range_by_right(a..b)
+                .eq(right.iter().filter(|(r, _)| (a..b).contains(*r))));
+        }
+        let copy = map.clone();
+        assert_eq!(copy, map);
+        assert_eq!(copy, left.into_iter().collect());
+        map.clear();
+        assert!(map.is_empty() && map.iter_right().next().is_none());
+    }
+}
+
+#[test]
+fn rbtree_multiset() {
+    use rand::Rng;
+    use std::collections::BTreeMap;
+    use xsl::collections::RBTreeMultiSet;
+    let mut rng = rand::thread_rng();
+    for _ in 0..100 {
+        let mut set = RBTreeMultiSet::new();
+        let mut counts = BTreeMap::new();
+        let size = rng.gen_range(1..100);
+        for _ in 0..500 {
+            let value = rng.gen_range(0..size);
+            let n = rng.gen_range(0..4);
+            match rng.gen_range(0..5) {
+                0 | 1 => {
+                    *counts.entry(value).or_insert(0) += 1;
+                    assert_eq!(set.insert(value), counts[&value]);
+                }
+                2 => {
+                    if n > 0 {
+                        *counts.entry(value).or_insert(0) += n;
+                    }
+                    assert_eq!(
+                        set.insert_many(value, n),
+                        counts.get(&value).copied().unwrap_or(0)
+                    );
+                }
+                3 => {
+                    let count = counts.get(&value).copied().unwrap_or(0);
+                    assert_eq!(set.remove_many(&value, n), n.min(count));
+                    if n >= count {
+                        counts.remove(&value);
+                    } else {
+                        *counts.get_mut(&value).unwrap() -= n;
+                    }
+                }
+                _ => assert_eq!(set.remove_all(&value), counts.remove(&value).unwrap_or(0)),
+            }
+            let sorted: Vec<_> = counts
+                .iter()
+                .flat_map(|(&value, &count)| std::iter::repeat_n(value, count))
+                .collect();
+            assert_eq!(set.len(), sorted.len());
+            assert_eq!(set.distinct_len(), counts.len());
+            assert!(set
+                .iter()
+                .map(|(v, c)| (*v, c))
+                .eq(counts.iter().map(|(v, c)| (*v, *c))));
+            for (i, value) in sorted.iter().enumerate() {
+                assert_eq!(set.nth(i), Some(value));
+            }
+            assert_eq!(set.nth(sorted.len()), None);
+            let value = rng.gen_range(-1..size + 1);
+            assert_eq!(set.rank(&value), sorted.partition_point(|&v| v < value));
+            assert_eq!(set.count(&value), counts.get(&value).copied().unwrap_or(0));
+            let q = rng.gen_range(0.0..=1.0);
+            let expected = sorted.get(((sorted.len().max(1) - 1) as f64 * q) as usize);
+            assert_eq!(set.quantile(q), expected);
+            let median = sorted.len().checked_sub(1).map(|last| &sorted[last / 2]);
+            assert_eq!(set.median(), median);
+        }
+        let copy = set.clone();
+        assert_eq!(copy, set);
+        assert_eq!(copy.median(), set.median());
+        set.clear();
+        assert!(set.is_empty() && set.median().is_none());
+    }
+}
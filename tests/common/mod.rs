@@ -22,6 +22,7 @@ where
     }
 }
 
+#[cfg(not(feature = "paranoid"))]
 pub fn timing<F>(f: F) -> std::time::Duration
 where
     F: FnOnce(),